@@ -0,0 +1,606 @@
+//! Command line and configuration file handling
+//!
+//! Every knob can be set in an optional TOML file given with `--config`. Flags given on the
+//! command line take precedence over the file, which takes precedence over the defaults.
+
+use crate::sim::MAX_HIDDEN_NEURONS;
+
+/// Evolve little brains
+#[derive(clap::Parser)]
+#[command(version)]
+struct Cli {
+    /// TOML configuration file
+    #[arg(short, long)]
+    config: Option<std::path::PathBuf>,
+
+    /// Seed for every random decision of the run
+    #[arg(long)]
+    seed: Option<u64>,
+
+    /// Days in a generation
+    #[arg(long)]
+    days: Option<usize>,
+
+    /// Width and height of the world
+    #[arg(long)]
+    size: Option<u8>,
+
+    /// Number of boops in every generation
+    #[arg(long)]
+    boops: Option<usize>,
+
+    /// Amount of food in the world
+    #[arg(long)]
+    food: Option<usize>,
+
+    /// Number of genes in a random genome
+    #[arg(long)]
+    synapses: Option<u16>,
+
+    /// Number of hidden neurons a genome may wire
+    #[arg(long)]
+    hidden_neurons: Option<u8>,
+
+    /// Chance of an offspring having one of its genes hit by a point mutation
+    #[arg(long)]
+    mutation_rate: Option<f32>,
+
+    /// Chance of each gene being hit by a point mutation, on top of `--mutation-rate`
+    #[arg(long)]
+    gene_mutation_rate: Option<f32>,
+
+    /// Write a snapshot here at the end of every generation
+    #[arg(long)]
+    save: Option<std::path::PathBuf>,
+
+    /// Resume from a snapshot instead of starting a new world
+    #[arg(long)]
+    load: Option<std::path::PathBuf>,
+
+    /// Write the gene pool here at the end of every generation
+    #[arg(long)]
+    export: Option<std::path::PathBuf>,
+
+    /// Seed the first generation from a gene pool instead of random genomes
+    #[arg(long)]
+    import: Option<std::path::PathBuf>,
+
+    /// Write statistics of every generation here, appending to them when resuming with `--load`
+    #[arg(long)]
+    stats: Option<std::path::PathBuf>,
+
+    /// Layout of the statistics file
+    #[arg(long, value_enum)]
+    stats_format: Option<crate::engine::Format>,
+
+    #[command(subcommand)]
+    engine: Option<Command>,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// Render every day in a window
+    Window {
+        /// Width of the window in pixels
+        #[arg(long)]
+        width: Option<i32>,
+
+        /// Height of the window in pixels
+        #[arg(long)]
+        height: Option<i32>,
+    },
+    /// Render every day in the terminal
+    Terminal {
+        /// Draw a border around the world
+        #[arg(long)]
+        border: Option<bool>,
+
+        /// Redraw in place instead of scrolling
+        #[arg(long)]
+        clear: Option<bool>,
+    },
+    /// Run without rendering, printing a summary per generation
+    Headless {
+        /// Stop after this many generations
+        #[arg(long)]
+        generations: Option<usize>,
+    },
+}
+
+#[derive(Copy, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Engine {
+    #[default]
+    Window,
+    Terminal,
+    Headless,
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Config {
+    pub seed: Option<u64>,
+    pub days: usize,
+    pub engine: Engine,
+    pub world: World,
+    pub genome: Genome,
+    pub evolution: Evolution,
+    pub window: Window,
+    pub terminal: Terminal,
+    pub headless: Headless,
+    pub files: Files,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            seed: None,
+            days: 300,
+            engine: Engine::default(),
+            world: World::default(),
+            genome: Genome::default(),
+            evolution: Evolution::default(),
+            window: Window::default(),
+            terminal: Terminal::default(),
+            headless: Headless::default(),
+            files: Files::default(),
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct World {
+    pub size: u8,
+    pub boops: usize,
+    pub food: usize,
+    pub forage: crate::sim::Forage,
+    pub terrain: Terrain,
+    pub hazard: crate::sim::Hazard,
+    pub metabolism: Option<crate::sim::Metabolism>,
+    pub collision: Option<crate::sim::Collision>,
+}
+
+/// Obstacles of a new world
+#[derive(Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Terrain {
+    #[default]
+    Open,
+    /// Text map, see [`crate::sim::Terrain::parse`]
+    Map(std::path::PathBuf),
+    /// Random rectangles of walls and lava covering about `density` and `lava` of the world
+    Scattered {
+        density: f32,
+        #[serde(default)]
+        lava: f32,
+    },
+}
+
+impl Default for World {
+    fn default() -> Self {
+        let parameters = crate::sim::Parameters::default();
+        Self {
+            size: 64,
+            boops: 500,
+            food: parameters.food,
+            forage: parameters.forage,
+            terrain: Terrain::default(),
+            hazard: parameters.hazard,
+            metabolism: parameters.metabolism,
+            collision: parameters.collision,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Genome {
+    pub synapses: u16,
+    pub hidden_neurons: u8,
+    /// Activation function of every neuron
+    pub activation: crate::neural::Activation,
+    /// Whether hidden neurons use the activation function picked by their genes instead
+    pub evolved_activation: bool,
+}
+
+impl Default for Genome {
+    fn default() -> Self {
+        Self {
+            synapses: 16,
+            hidden_neurons: 4,
+            activation: crate::neural::Activation::default(),
+            evolved_activation: false,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Evolution {
+    pub crossover: crate::sim::Crossover,
+    pub mutation: crate::sim::Mutation,
+    pub selection: crate::sim::Selection,
+    pub reproduction: crate::sim::Reproduction,
+}
+
+impl Default for Evolution {
+    fn default() -> Self {
+        let parameters = crate::sim::Parameters::default();
+        Self {
+            crossover: parameters.crossover,
+            mutation: parameters.mutation,
+            selection: parameters.selection,
+            reproduction: parameters.reproduction,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Window {
+    pub width: i32,
+    pub height: i32,
+}
+
+impl Default for Window {
+    fn default() -> Self {
+        Self {
+            width: 800,
+            height: 800,
+        }
+    }
+}
+
+#[derive(serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Terminal {
+    pub border: bool,
+    pub clear: bool,
+}
+
+impl Default for Terminal {
+    fn default() -> Self {
+        Self {
+            border: true,
+            clear: true,
+        }
+    }
+}
+
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Headless {
+    pub generations: Option<usize>,
+}
+
+#[derive(Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Files {
+    pub save: Option<std::path::PathBuf>,
+    pub load: Option<std::path::PathBuf>,
+    pub export: Option<std::path::PathBuf>,
+    pub import: Option<std::path::PathBuf>,
+    pub stats: Option<std::path::PathBuf>,
+    pub stats_format: crate::engine::Format,
+}
+
+impl Config {
+    /// Builds the configuration from the command line and the file it points to
+    pub fn parse() -> anyhow::Result<Self> {
+        let cli = <Cli as clap::Parser>::parse();
+
+        let mut config = if let Some(path) = &cli.config {
+            Self::read(path)?
+        } else {
+            Self::default()
+        };
+
+        config.apply(cli);
+        config.validate()?;
+        Ok(config)
+    }
+
+    fn read(path: &std::path::Path) -> anyhow::Result<Self> {
+        use anyhow::Context;
+
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Could not read {}", path.display()))?;
+        toml::from_str(&text)
+            .with_context(|| format!("Invalid configuration in {}", path.display()))
+    }
+
+    fn apply(&mut self, cli: Cli) {
+        fn set<T>(target: &mut T, value: Option<T>) {
+            if let Some(value) = value {
+                *target = value;
+            }
+        }
+
+        fn set_some<T>(target: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *target = value;
+            }
+        }
+
+        set_some(&mut self.seed, cli.seed);
+        set(&mut self.days, cli.days);
+        set(&mut self.world.size, cli.size);
+        set(&mut self.world.boops, cli.boops);
+        set(&mut self.world.food, cli.food);
+        set(&mut self.genome.synapses, cli.synapses);
+        set(&mut self.genome.hidden_neurons, cli.hidden_neurons);
+        set(&mut self.evolution.mutation.rate, cli.mutation_rate);
+        set(
+            &mut self.evolution.mutation.gene_rate,
+            cli.gene_mutation_rate,
+        );
+        set_some(&mut self.files.save, cli.save);
+        set_some(&mut self.files.load, cli.load);
+        set_some(&mut self.files.export, cli.export);
+        set_some(&mut self.files.import, cli.import);
+        set_some(&mut self.files.stats, cli.stats);
+        set(&mut self.files.stats_format, cli.stats_format);
+
+        match cli.engine {
+            None => {}
+            Some(Command::Window { width, height }) => {
+                self.engine = Engine::Window;
+                set(&mut self.window.width, width);
+                set(&mut self.window.height, height);
+            }
+            Some(Command::Terminal { border, clear }) => {
+                self.engine = Engine::Terminal;
+                set(&mut self.terminal.border, border);
+                set(&mut self.terminal.clear, clear);
+            }
+            Some(Command::Headless { generations }) => {
+                self.engine = Engine::Headless;
+                set_some(&mut self.headless.generations, generations);
+            }
+        }
+    }
+
+    fn validate(&self) -> anyhow::Result<()> {
+        if self.days == 0 {
+            anyhow::bail!("`days` must be at least 1");
+        }
+
+        if self.files.load.is_some() {
+            if self.files.import.is_some() {
+                anyhow::bail!(
+                    "`import` cannot be used with `load`: the snapshot already has a population"
+                );
+            }
+            if self.seed.is_some() {
+                anyhow::bail!(
+                    "`seed` cannot be used with `load`: the snapshot carries its own seed"
+                );
+            }
+            if !matches!(self.world.terrain, Terrain::Open) {
+                anyhow::bail!(
+                    "`world.terrain` cannot be used with `load`: the snapshot has its own terrain"
+                );
+            }
+        } else {
+            if self.world.size < 2 {
+                anyhow::bail!("`world.size` must be at least 2, got {}", self.world.size);
+            }
+            if self.world.boops == 0 {
+                anyhow::bail!("`world.boops` must be at least 1");
+            }
+            if self.files.import.is_none() {
+                if self.genome.synapses == 0 {
+                    anyhow::bail!("`genome.synapses` must be at least 1");
+                }
+                if self.genome.hidden_neurons > MAX_HIDDEN_NEURONS {
+                    anyhow::bail!(
+                        "`genome.hidden_neurons` must be at most {MAX_HIDDEN_NEURONS}, got {}",
+                        self.genome.hidden_neurons
+                    );
+                }
+            }
+        }
+
+        if self.world.food == 0 {
+            anyhow::bail!("`world.food` must be at least 1, otherwise no boop can ever survive");
+        }
+
+        self.world
+            .forage
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `world.forage`: {e}"))?;
+
+        if self.world.forage.consumed && self.evolution.selection.on_food() {
+            anyhow::bail!(
+                "`evolution.selection` cannot keep boops `on_food` when `world.forage.consumed` \
+                 is set: eating removes the food a boop stands on, select by `eaten` instead"
+            );
+        }
+
+        self.world
+            .hazard
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `world.hazard`: {e}"))?;
+
+        if let Some(metabolism) = &self.world.metabolism {
+            metabolism
+                .validate()
+                .map_err(|e| anyhow::anyhow!("Invalid `world.metabolism`: {e}"))?;
+        }
+
+        if let Some(collision) = &self.world.collision {
+            collision
+                .validate()
+                .map_err(|e| anyhow::anyhow!("Invalid `world.collision`: {e}"))?;
+        }
+
+        self.evolution
+            .crossover
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `evolution.crossover`: {e}"))?;
+
+        self.evolution
+            .mutation
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `evolution.mutation`: {e}"))?;
+
+        self.evolution
+            .selection
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `evolution.selection`: {e}"))?;
+
+        self.evolution
+            .reproduction
+            .validate()
+            .map_err(|e| anyhow::anyhow!("Invalid `evolution.reproduction`: {e}"))?;
+
+        if self.window.width <= 0 || self.window.height <= 0 {
+            anyhow::bail!(
+                "`window.width` and `window.height` must be positive, got {}x{}",
+                self.window.width,
+                self.window.height
+            );
+        }
+
+        if self.headless.generations == Some(0) {
+            anyhow::bail!("`headless.generations` must be at least 1");
+        }
+
+        Ok(())
+    }
+
+    /// Rules of the simulation, reading the map if there is one
+    pub fn parameters(&self) -> anyhow::Result<crate::sim::Parameters> {
+        use anyhow::Context;
+
+        let landscape = match &self.world.terrain {
+            Terrain::Open => crate::sim::Landscape::Open,
+            Terrain::Map(path) => {
+                let text = std::fs::read_to_string(path)
+                    .with_context(|| format!("Could not read {}", path.display()))?;
+                crate::sim::Landscape::Map(
+                    crate::sim::Terrain::parse(&text)
+                        .with_context(|| format!("Invalid map in {}", path.display()))?,
+                )
+            }
+            Terrain::Scattered { density, lava } => crate::sim::Landscape::Scattered {
+                density: *density,
+                lava: *lava,
+            },
+        };
+        landscape
+            .validate(self.world.size)
+            .map_err(|e| anyhow::anyhow!("Invalid `world.terrain`: {e}"))?;
+
+        Ok(crate::sim::Parameters {
+            food: self.world.food,
+            forage: self.world.forage.clone(),
+            landscape,
+            hazard: self.world.hazard.clone(),
+            metabolism: self.world.metabolism.clone(),
+            collision: self.world.collision.clone(),
+            neural: crate::sim::Neural {
+                activation: self.genome.activation,
+                evolved: self.genome.evolved_activation,
+            },
+            crossover: self.evolution.crossover.clone(),
+            mutation: self.evolution.mutation.clone(),
+            selection: self.evolution.selection.clone(),
+            reproduction: self.evolution.reproduction.clone(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Config;
+
+    #[test]
+    fn partial_file() {
+        let config: Config = toml::from_str(
+            r#"
+            days = 10
+            engine = "headless"
+
+            [world]
+            size = 16
+            terrain = { scattered = { density = 0.2, lava = 0.1 } }
+            hazard = { damage = 0.25 }
+            metabolism = { movement = 0.02, meal = 0.5 }
+            collision = { radius = 0.4, contact = "push" }
+
+            [world.forage]
+            consumed = true
+            respawn = 0.2
+            spawn = { clustered = { clusters = 3, spread = 4.0 } }
+            growth = { rate = 0.1 }
+
+            [genome]
+            activation = "relu"
+            evolved_activation = true
+
+            [terminal]
+            border = false
+
+            [evolution]
+            selection = { all = [{ eaten = 1 }, { top = { count = 4, fitness = "travelled" } }] }
+            crossover = "aligned"
+
+            [evolution.mutation]
+            rate = 0.01
+            gene_rate = 0.001
+            limit = 2
+            kinds = { nudge = 1.0 }
+            deletion = 0.1
+            traits = 0.05
+            activations = 0.02
+            biases = 0.1
+
+            [evolution.reproduction]
+            mating = { tournament = { size = 3, fitness = "eaten" } }
+            elitism = { count = 2, fitness = "energy" }
+            selfing = false
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(config.days, 10);
+        assert_eq!(config.world.size, 16);
+        assert_eq!(config.world.boops, Config::default().world.boops);
+        assert_eq!(config.genome.activation, crate::neural::Activation::Relu);
+        assert!(!config.terminal.border);
+        assert!(config.terminal.clear);
+        assert!(config.validate().is_ok());
+    }
+
+    #[test]
+    fn validation() {
+        let mut config = Config::default();
+        config.world.food = 0;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.evolution.mutation.rate = 1.5;
+        assert!(config.validate().is_err());
+
+        let mut config = Config::default();
+        config.evolution.mutation.gene_rate = -0.1;
+        assert!(config.validate().is_err());
+
+        // Eating the food would leave nobody on food at the end of the day
+        let mut config = Config::default();
+        config.world.forage.consumed = true;
+        assert!(config.validate().is_err());
+        config.evolution.selection =
+            crate::sim::Selection::Any(vec![crate::sim::Selection::OnFood]);
+        assert!(config.validate().is_err());
+        config.evolution.selection = crate::sim::Selection::Eaten(1);
+        assert!(config.validate().is_ok());
+
+        let mut config = Config::default();
+        config.files.load = Some("a".into());
+        config.files.import = Some("b".into());
+        assert!(config.validate().is_err());
+    }
+}
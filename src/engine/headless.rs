@@ -0,0 +1,79 @@
+use super::{Engine, Hooks};
+use crate::sim::{Mutations, Simulation};
+
+pub struct Headless {
+    generations: Option<usize>,
+}
+
+impl Headless {
+    pub fn new(generations: Option<usize>) -> Self {
+        Self { generations }
+    }
+}
+
+impl Engine for Headless {
+    fn start(
+        self,
+        mut simulation: Simulation,
+        days: usize,
+        mut hooks: Hooks,
+    ) -> anyhow::Result<()> {
+        let start = std::time::Instant::now();
+
+        for _ in 0..self.generations.unwrap_or(usize::MAX) {
+            let now = std::time::Instant::now();
+            let generation = simulation.generation();
+
+            for _ in 0..days {
+                simulation.step();
+            }
+
+            let count = simulation.population();
+            // Counted before the offspring replace the generation being reported
+            let signatures = signatures(&simulation);
+            let mutations = simulation.mutations();
+            let survivors = simulation.next_generation();
+
+            report(
+                generation, survivors, count, signatures, mutations, now, start,
+            );
+
+            if survivors == 0 {
+                hooks.extinction(&simulation)?;
+                break;
+            }
+
+            hooks.generation(&simulation)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How many distinct signatures the boops carry
+fn signatures(simulation: &Simulation) -> usize {
+    simulation
+        .boops()
+        .map(|b| b.signature())
+        .collect::<std::collections::HashSet<_>>()
+        .len()
+}
+
+fn report(
+    generation: usize,
+    survivors: usize,
+    count: usize,
+    signatures: usize,
+    mutations: Mutations,
+    now: std::time::Instant,
+    start: std::time::Instant,
+) {
+    use std::io::Write;
+
+    let _ = writeln!(
+        std::io::stdout().lock(),
+        "Generation: {generation} Survivors: {survivors}/{count} Signatures: {signatures} Mutations: [{mutations}] Elapsed: {:?} Total: {:?}",
+        now.elapsed(),
+        start.elapsed(),
+    );
+}
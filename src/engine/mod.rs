@@ -7,6 +7,84 @@ pub use terminal::Terminal;
 mod quad;
 pub use quad::Quad;
 
+mod headless;
+pub use headless::Headless;
+
+mod statistics;
+pub use statistics::{Format, Recorder};
+
 pub trait Engine {
-    fn start(self, simulation: Simulation, days: usize);
+    fn start(self, simulation: Simulation, days: usize, hooks: Hooks) -> anyhow::Result<()>;
+}
+
+/// Work to be done at the end of every generation, regardless of the engine
+#[derive(Default)]
+pub struct Hooks {
+    snapshot: Option<std::path::PathBuf>,
+    population: Option<std::path::PathBuf>,
+    statistics: Option<Recorder>,
+}
+
+impl Hooks {
+    pub fn snapshot(mut self, path: std::path::PathBuf) -> Self {
+        self.snapshot = Some(path);
+        self
+    }
+
+    pub fn population(mut self, path: std::path::PathBuf) -> Self {
+        self.population = Some(path);
+        self
+    }
+
+    pub fn statistics(mut self, recorder: Recorder) -> Self {
+        self.statistics = Some(recorder);
+        self
+    }
+
+    fn generation(&mut self, simulation: &Simulation) -> anyhow::Result<()> {
+        if let Some(path) = &self.snapshot {
+            write_atomically(path, |writer| simulation.save(writer))?;
+        }
+
+        if let Some(path) = &self.population {
+            write_atomically(path, |writer| simulation.export(writer))?;
+        }
+
+        if let Some(recorder) = &mut self.statistics {
+            recorder.record(simulation)?;
+        }
+
+        Ok(())
+    }
+
+    /// Work to be done when nobody survived the current generation
+    ///
+    /// The snapshot and gene pool of the last generation are kept, as an extinct one could not be
+    /// resumed
+    fn extinction(&mut self, simulation: &Simulation) -> anyhow::Result<()> {
+        if let Some(recorder) = &mut self.statistics {
+            recorder.extinction(simulation)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Writes to the side and renames so that a crash never leaves a truncated file behind
+fn write_atomically(
+    path: &std::path::Path,
+    write: impl FnOnce(&mut std::io::BufWriter<std::fs::File>) -> std::io::Result<()>,
+) -> anyhow::Result<()> {
+    use anyhow::Context;
+
+    let partial = path.with_extension("partial");
+    let mut writer = std::io::BufWriter::new(
+        std::fs::File::create(&partial)
+            .with_context(|| format!("Could not create {}", partial.display()))?,
+    );
+    write(&mut writer)
+        .and_then(|()| std::io::Write::flush(&mut writer))
+        .with_context(|| format!("Could not write to {}", partial.display()))?;
+    std::fs::rename(&partial, path)
+        .with_context(|| format!("Could not move {} to {}", partial.display(), path.display()))
 }
@@ -1,5 +1,5 @@
-use super::Engine;
-use crate::sim::Simulation;
+use super::{Engine, Hooks};
+use crate::sim::{Accessor, Simulation, Tile};
 
 pub struct Quad(macroquad::window::Conf);
 
@@ -11,26 +11,34 @@ impl Quad {
 }
 
 impl Engine for Quad {
-    fn start(self, mut simulation: Simulation, days: usize) {
+    fn start(self, simulation: Simulation, days: usize, hooks: Hooks) -> anyhow::Result<()> {
+        let result = std::rc::Rc::new(std::cell::RefCell::new(Ok(())));
+
+        let output = result.clone();
         macroquad::Window::from_config(self.0, async move {
-            let mut generation = 0_usize;
-            loop {
-                for day in 0..days {
-                    if macroquad::input::is_key_down(macroquad::input::KeyCode::Escape) {
-                        return;
-                    }
-
-                    simulation.step();
-                    render(&simulation, generation, day);
-                    macroquad::window::next_frame().await;
-                }
-
-                generation += 1;
-                if !simulation.next_generation() {
-                    break;
-                }
-            }
+            *output.borrow_mut() = run(simulation, days, hooks).await;
         });
+
+        result.replace(Ok(()))
+    }
+}
+
+async fn run(mut simulation: Simulation, days: usize, mut hooks: Hooks) -> anyhow::Result<()> {
+    loop {
+        for day in 0..days {
+            if macroquad::input::is_key_down(macroquad::input::KeyCode::Escape) {
+                return Ok(());
+            }
+
+            simulation.step();
+            render(&simulation, simulation.generation(), day);
+            macroquad::window::next_frame().await;
+        }
+
+        if simulation.next_generation() == 0 {
+            return hooks.extinction(&simulation);
+        }
+        hooks.generation(&simulation)?;
     }
 }
 
@@ -56,10 +64,31 @@ fn render(simulation: &Simulation, generation: usize, day: usize) {
         macroquad::color::WHITE,
     );
 
-    for boop in simulation.boops() {
+    for (tile, color) in [
+        (
+            Tile::Wall,
+            macroquad::color::Color::from_rgba(99, 99, 99, 255),
+        ),
+        (
+            Tile::Lava,
+            macroquad::color::Color::from_rgba(207, 72, 16, 255),
+        ),
+    ] {
+        for (x, y) in simulation.terrain().tiles(tile) {
+            macroquad::shapes::draw_rectangle(
+                scale2 + f32::from(x) * scale,
+                20. + scale2 + f32::from(y) * scale,
+                scale,
+                scale,
+                color,
+            );
+        }
+    }
+
+    for boop in simulation.boops().filter(Accessor::alive) {
         let coord = boop.coordinate();
         let direction = boop.direction().as_rad().to_degrees();
-        let color = signature_to_color(boop.signature());
+        let color = rgb_to_color(boop.colour());
 
         macroquad::shapes::draw_poly(
             scale2 + coord.x() * scale,
@@ -81,12 +110,12 @@ fn render(simulation: &Simulation, generation: usize, day: usize) {
     }
 }
 
-fn signature_to_color(mut signature: u32) -> macroquad::color::Color {
-    let b = truncate!(u32 -> u8, signature & 0xff);
-    signature >>= 8;
-    let g = truncate!(u32 -> u8, signature & 0xff);
-    signature >>= 8;
-    let r = truncate!(u32 -> u8, signature & 0xff);
+fn rgb_to_color(mut rgb: u32) -> macroquad::color::Color {
+    let b = truncate!(u32 -> u8, rgb & 0xff);
+    rgb >>= 8;
+    let g = truncate!(u32 -> u8, rgb & 0xff);
+    rgb >>= 8;
+    let r = truncate!(u32 -> u8, rgb & 0xff);
 
     macroquad::color::Color::from_rgba(r, g, b, 0xff)
 }
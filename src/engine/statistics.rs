@@ -0,0 +1,167 @@
+use crate::sim::{Simulation, Statistics};
+
+/// Layout of the statistics file
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, clap::ValueEnum, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Format {
+    /// Comma separated values, with a header line
+    #[default]
+    Csv,
+    /// One JSON object per line
+    Json,
+}
+
+const COLUMNS: &str = "generation,population,survivors,survival_rate,signatures,dominant,dominant_share,genome_length,weight_mean,weight_variance,direct,into_hidden,inter_hidden,from_hidden,speed,turn_rate,size,sensing";
+
+/// Appends the [`Statistics`] of every generation to a file
+pub struct Recorder {
+    writer: std::io::BufWriter<std::fs::File>,
+    format: Format,
+    path: std::path::PathBuf,
+}
+
+impl Recorder {
+    /// Creates the file, replacing any previous run, or appends to it when `resume` is set so
+    /// that a run restarted from a snapshot carries on where it left off
+    pub fn create(path: std::path::PathBuf, format: Format, resume: bool) -> anyhow::Result<Self> {
+        use anyhow::Context;
+        use std::io::Write;
+
+        let file = std::fs::OpenOptions::new()
+            .write(true)
+            .create(true)
+            .append(resume)
+            .truncate(!resume)
+            .open(&path)
+            .with_context(|| format!("Could not create {}", path.display()))?;
+        let empty = file
+            .metadata()
+            .with_context(|| format!("Could not read {}", path.display()))?
+            .len()
+            == 0;
+
+        let mut writer = std::io::BufWriter::new(file);
+        if format == Format::Csv && empty {
+            writeln!(writer, "{COLUMNS}")
+                .with_context(|| format!("Could not write to {}", path.display()))?;
+        }
+
+        Ok(Self {
+            writer,
+            format,
+            path,
+        })
+    }
+
+    pub fn record(&mut self, simulation: &Simulation) -> anyhow::Result<()> {
+        self.write(&Statistics::collect(simulation))
+    }
+
+    /// Records the generation that died out, with no population
+    pub fn extinction(&mut self, simulation: &Simulation) -> anyhow::Result<()> {
+        self.write(&Statistics::extinct(simulation))
+    }
+
+    fn write(&mut self, statistics: &Statistics) -> anyhow::Result<()> {
+        use anyhow::Context;
+        use std::io::Write;
+
+        match self.format {
+            Format::Csv => write_csv(&mut self.writer, statistics),
+            Format::Json => write_json(&mut self.writer, statistics),
+        }
+        // Flushed every generation so that a run can be plotted while it goes
+        .and_then(|()| self.writer.flush())
+        .with_context(|| format!("Could not write to {}", self.path.display()))
+    }
+}
+
+fn write_csv(writer: &mut impl std::io::Write, s: &Statistics) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        "{},{},{},{},{},{:08x},{},{},{},{},{},{},{},{},{},{},{},{}",
+        s.generation,
+        s.population,
+        s.survivors,
+        s.survival_rate,
+        s.signatures,
+        s.dominant,
+        s.dominant_share,
+        s.genome_length,
+        s.weight_mean,
+        s.weight_variance,
+        s.direct,
+        s.into_hidden,
+        s.inter_hidden,
+        s.from_hidden,
+        s.speed,
+        s.turn_rate,
+        s.size,
+        s.sensing,
+    )
+}
+
+fn write_json(writer: &mut impl std::io::Write, s: &Statistics) -> std::io::Result<()> {
+    writeln!(
+        writer,
+        r#"{{"generation":{},"population":{},"survivors":{},"survival_rate":{},"signatures":{},"dominant":"{:08x}","dominant_share":{},"genome_length":{},"weight_mean":{},"weight_variance":{},"direct":{},"into_hidden":{},"inter_hidden":{},"from_hidden":{},"speed":{},"turn_rate":{},"size":{},"sensing":{}}}"#,
+        s.generation,
+        s.population,
+        s.survivors,
+        s.survival_rate,
+        s.signatures,
+        s.dominant,
+        s.dominant_share,
+        s.genome_length,
+        s.weight_mean,
+        s.weight_variance,
+        s.direct,
+        s.into_hidden,
+        s.inter_hidden,
+        s.from_hidden,
+        s.speed,
+        s.turn_rate,
+        s.size,
+        s.sensing,
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Format, Recorder};
+    use crate::sim::{Genesis, Parameters, Simulation};
+
+    #[test]
+    fn resume() {
+        let path = std::env::temp_dir().join(format!("geny-resume-{}.csv", std::process::id()));
+        let simulation = Simulation::new(
+            27,
+            8,
+            4,
+            Genesis::Random {
+                synapses: 4,
+                hidden_neurons: 2,
+            },
+            Parameters::default(),
+        );
+
+        Recorder::create(path.clone(), Format::Csv, false)
+            .unwrap()
+            .record(&simulation)
+            .unwrap();
+        // The generation that follows, which dies out
+        Recorder::create(path.clone(), Format::Csv, true)
+            .unwrap()
+            .extinction(&simulation)
+            .unwrap();
+
+        let text = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3, "{text}");
+        assert!(lines[0].starts_with("generation,"));
+        assert!(lines[1].starts_with("0,"));
+        assert!(lines[2].starts_with("1,"));
+    }
+}
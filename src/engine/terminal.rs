@@ -1,24 +1,30 @@
-use super::Engine;
-use crate::sim::Simulation;
+use super::{Engine, Hooks};
+use crate::sim::{Accessor, Simulation, Tile};
 
 pub struct Terminal<const BORDER: bool, const CLEAR: bool>;
 
 impl<const BORDER: bool, const CLEAR: bool> Engine for Terminal<BORDER, CLEAR> {
-    fn start(self, mut simulation: Simulation, days: usize) {
-        let mut generation = 0_usize;
-
+    fn start(
+        self,
+        mut simulation: Simulation,
+        days: usize,
+        mut hooks: Hooks,
+    ) -> anyhow::Result<()> {
         let mut buffer = vec![vec![None; simulation.size() as usize]; simulation.size() as usize];
 
         loop {
             for day in 0..days {
                 simulation.step();
-                render::<BORDER, CLEAR>(&simulation, generation, day, &mut buffer);
+                render::<BORDER, CLEAR>(&simulation, simulation.generation(), day, &mut buffer);
             }
-            generation += 1;
-            if !simulation.next_generation() {
+            if simulation.next_generation() == 0 {
+                hooks.extinction(&simulation)?;
                 break;
             }
+            hooks.generation(&simulation)?;
         }
+
+        Ok(())
     }
 }
 
@@ -26,7 +32,7 @@ fn render<const BORDER: bool, const CLEAR: bool>(
     simulation: &Simulation,
     generation: usize,
     day: usize,
-    buffer: &mut Vec<Vec<Option<(char, u32)>>>,
+    buffer: &mut [Vec<Option<(char, u32)>>],
 ) {
     use std::io::Write;
 
@@ -55,7 +61,7 @@ fn render<const BORDER: bool, const CLEAR: bool>(
 
     buffer.iter_mut().flatten().for_each(|c| *c = None);
 
-    for boop in simulation.boops() {
+    for boop in simulation.boops().filter(Accessor::alive) {
         let coord = boop.coordinate();
         let direction = {
             let dir = boop.direction().as_rad();
@@ -71,28 +77,28 @@ fn render<const BORDER: bool, const CLEAR: bool>(
                 '→'
             }
         };
-        let signature = boop.signature();
+        let colour = boop.colour();
 
         // SAFETY: Coordinate is always in the 0..size range
         unsafe {
             *buffer
                 .get_unchecked_mut(usize::from(coord.y_index()))
-                .get_unchecked_mut(usize::from(coord.x_index())) = Some((direction, signature));
+                .get_unchecked_mut(usize::from(coord.x_index())) = Some((direction, colour));
         }
     }
 
-    for row in buffer {
+    for (y, row) in (0_u8..).zip(buffer.iter()) {
         if BORDER {
             print!("┃");
         }
 
-        for cell in row {
-            if let Some((direction, mut signature)) = cell {
-                let b = signature & 0xff;
-                signature >>= 8;
-                let g = signature & 0xff;
-                signature >>= 8;
-                let r = signature & 0xff;
+        for (x, cell) in (0_u8..).zip(row) {
+            if let Some((direction, mut colour)) = *cell {
+                let b = colour & 0xff;
+                colour >>= 8;
+                let g = colour & 0xff;
+                colour >>= 8;
+                let r = colour & 0xff;
                 print!(
                     "[48;2;{r};{g};{b}m[38;2;{};{};{}m{direction}[38;2;{r};{g};{b}m\u{2588}[m",
                     !r & 0xff,
@@ -100,7 +106,11 @@ fn render<const BORDER: bool, const CLEAR: bool>(
                     !b & 0xff,
                 );
             } else {
-                print!("  ");
+                match simulation.terrain().tile(x, y) {
+                    Tile::Open => print!("  "),
+                    Tile::Wall => print!("[38;5;242m\u{2588}\u{2588}[m"),
+                    Tile::Lava => print!("[38;5;202m\u{2592}\u{2592}[m"),
+                }
             }
         }
 
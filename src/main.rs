@@ -2,7 +2,7 @@
 
 macro_rules! build_vec {
     ($builder:expr, $size:expr) => {{
-        let builder = $builder;
+        let mut builder = $builder;
         (0..$size).map(|_| builder()).collect()
     }};
 }
@@ -14,57 +14,99 @@ macro_rules! truncate {
     }};
 }
 
+mod config;
 mod engine;
 mod neural;
 mod sim;
 
 fn main() -> anyhow::Result<()> {
-    let mut args = std::env::args().skip(1);
+    let config = config::Config::parse()?;
 
-    let size = args
-        .next()
-        .ok_or(anyhow::anyhow!("No size provided"))?
-        .parse()?;
+    let simulation = if let Some(path) = &config.files.load {
+        use anyhow::Context;
 
-    let beings = args
-        .next()
-        .ok_or(anyhow::anyhow!("No being count provided"))?
-        .parse()?;
+        sim::Simulation::load(&mut open(path)?, config.parameters()?)
+            .with_context(|| format!("Could not load snapshot from {}", path.display()))?
+    } else {
+        let genesis = if let Some(path) = &config.files.import {
+            use anyhow::Context;
+
+            let population = sim::Population::read(open(path)?)
+                .with_context(|| format!("Could not import population from {}", path.display()))?;
+            sim::Genesis::Population(population)
+        } else {
+            sim::Genesis::Random {
+                synapses: config.genome.synapses,
+                hidden_neurons: config.genome.hidden_neurons,
+            }
+        };
+
+        let seed = config.seed.unwrap_or_else(rand::random);
+        eprintln!("Seed: {seed}");
 
-    let days = args
-        .next()
-        .ok_or(anyhow::anyhow!("No days per generation provided"))?
-        .parse()?;
+        sim::Simulation::new(
+            seed,
+            config.world.size,
+            config.world.boops,
+            genesis,
+            config.parameters()?,
+        )
+    };
 
-    let synapses = args
-        .next()
-        .ok_or(anyhow::anyhow!("No synapse count provided"))?
-        .parse()?;
+    let mut hooks = engine::Hooks::default();
+    if let Some(path) = &config.files.save {
+        hooks = hooks.snapshot(path.clone());
+    }
+    if let Some(path) = &config.files.export {
+        hooks = hooks.population(path.clone());
+    }
+    if let Some(path) = &config.files.stats {
+        hooks = hooks.statistics(engine::Recorder::create(
+            path.clone(),
+            config.files.stats_format,
+            config.files.load.is_some(),
+        )?);
+    }
 
-    let hidden_neurons = args
-        .next()
-        .ok_or(anyhow::anyhow!("No hidden neuron count provided"))?
-        .parse()?;
+    start(simulation, &config, hooks)
+}
 
-    let terminal = args.next().is_some_and(|s| s.trim() == "t");
+fn start(
+    simulation: sim::Simulation,
+    config: &config::Config,
+    hooks: engine::Hooks,
+) -> anyhow::Result<()> {
+    use engine::Engine;
 
-    let simulation = sim::Simulation::new(size, beings, synapses, hidden_neurons);
+    let days = config.days;
 
-    if terminal {
-        use engine::Engine;
-        let terminal = engine::Terminal::<true, true>;
-        terminal.start(simulation, days);
-    } else {
-        use engine::Engine;
-        let quad = engine::Quad::new(macroquad::window::Conf {
-            window_title: String::from("Geny"),
-            window_width: 800,
-            window_height: 800,
-            window_resizable: false,
-            ..macroquad::window::Conf::default()
-        });
-        quad.start(simulation, days);
+    match config.engine {
+        config::Engine::Window => {
+            let quad = engine::Quad::new(macroquad::window::Conf {
+                window_title: String::from("Geny"),
+                window_width: config.window.width,
+                window_height: config.window.height,
+                window_resizable: false,
+                ..macroquad::window::Conf::default()
+            });
+            quad.start(simulation, days, hooks)
+        }
+        config::Engine::Terminal => match (config.terminal.border, config.terminal.clear) {
+            (true, true) => engine::Terminal::<true, true>.start(simulation, days, hooks),
+            (true, false) => engine::Terminal::<true, false>.start(simulation, days, hooks),
+            (false, true) => engine::Terminal::<false, true>.start(simulation, days, hooks),
+            (false, false) => engine::Terminal::<false, false>.start(simulation, days, hooks),
+        },
+        config::Engine::Headless => {
+            engine::Headless::new(config.headless.generations).start(simulation, days, hooks)
+        }
     }
+}
+
+fn open(path: &std::path::Path) -> anyhow::Result<std::io::BufReader<std::fs::File>> {
+    use anyhow::Context;
 
-    Ok(())
+    std::fs::File::open(path)
+        .map(std::io::BufReader::new)
+        .with_context(|| format!("Could not open {}", path.display()))
 }
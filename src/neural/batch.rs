@@ -0,0 +1,240 @@
+use super::{signal, Brain, Program, Response, Stimulus, Synapse};
+
+/// Most brains evaluated together in a block, one per lane
+const LANES: usize = 64;
+
+/// Index, synapses and biases of a brain waiting for a lane
+type Member = (usize, Vec<Synapse>, Vec<f32>);
+
+/// Brains evaluated together, grouped by topology
+///
+/// Brains compiled to the same program only differ by their synapses, biases and state. Every
+/// group is split into blocks of up to [`LANES`] brains, which store each synapse, bias and slot
+/// as a row holding one lane per brain, so that a tick runs every instruction of the program over
+/// contiguous lanes. Blocks are evaluated in parallel
+pub struct Batches<I: Copy + Eq, O: Copy + Eq, const H: u8> {
+    programs: Vec<Program<I, O>>,
+    blocks: Vec<Block>,
+    /// Block and lane of every brain
+    places: Vec<(usize, usize)>,
+}
+
+struct Block {
+    /// Index of the program in [`Batches::programs`]
+    program: usize,
+    /// Brain of every lane
+    brains: Vec<usize>,
+    /// Row of lanes for every dentrite
+    synapses: Vec<Synapse>,
+    /// Row of lanes for every neuron
+    biases: Vec<f32>,
+    /// Row of lanes for every slot
+    slots: Vec<f32>,
+    /// Sum of every lane for the neuron being computed
+    sums: Vec<f32>,
+    /// Stimuli of the senses of a single brain, as filled by the caller
+    stimuli: Vec<Stimulus>,
+}
+
+impl<I, O, const H: u8> Batches<I, O, H>
+where
+    I: Copy + Eq + std::hash::Hash + Sync,
+    O: Copy + Eq + std::hash::Hash + Sync,
+{
+    /// Groups `brains` by topology, starting from a blank state
+    pub fn new(brains: impl IntoIterator<Item = Brain<I, O, H>>) -> Self {
+        let mut indices = std::collections::HashMap::new();
+        let mut groups: Vec<Vec<Member>> = vec![];
+        let mut count = 0;
+
+        for (index, brain) in brains.into_iter().enumerate() {
+            let next = groups.len();
+            let group = *indices.entry(brain.program).or_insert(next);
+            if group == next {
+                groups.push(vec![]);
+            }
+            groups[group].push((index, brain.synapses, brain.biases));
+            count += 1;
+        }
+
+        let mut programs: Vec<_> = indices.into_iter().collect();
+        programs.sort_unstable_by_key(|(_, group)| *group);
+        let programs: Vec<_> = programs.into_iter().map(|(program, _)| program).collect();
+
+        let mut blocks = vec![];
+        let mut places = vec![(0, 0); count];
+        for (group, members) in groups.iter().enumerate() {
+            let program = &programs[group];
+            for lanes in members.chunks(LANES) {
+                for (lane, (brain, _, _)) in lanes.iter().enumerate() {
+                    places[*brain] = (blocks.len(), lane);
+                }
+
+                blocks.push(Block {
+                    program: group,
+                    brains: lanes.iter().map(|(brain, _, _)| *brain).collect(),
+                    synapses: (0..program.sources.len())
+                        .flat_map(|d| lanes.iter().map(move |(_, synapses, _)| synapses[d]))
+                        .collect(),
+                    biases: (0..program.neurons.len())
+                        .flat_map(|n| lanes.iter().map(move |(_, _, biases)| biases[n]))
+                        .collect(),
+                    slots: vec![0.; program.slots * lanes.len()],
+                    sums: vec![0.; lanes.len()],
+                    stimuli: vec![Stimulus::from(false); program.senses.len()],
+                });
+            }
+        }
+
+        Self {
+            programs,
+            blocks,
+            places,
+        }
+    }
+
+    /// Evaluates every brain once, with `sense` filling the stimuli of the senses a brain reads
+    ///
+    /// Brains are identified by their position when they were grouped
+    pub fn tick(&mut self, sense: impl Fn(usize, &[I], &mut [Stimulus]) + Sync) {
+        use rayon::prelude::*;
+
+        let programs = &self.programs;
+        self.blocks
+            .par_iter_mut()
+            .for_each(|block| block.tick(&programs[block.program], &sense));
+    }
+
+    /// What the brain at `index` responded on the last tick
+    pub fn responses(&self, index: usize) -> impl Iterator<Item = (O, Response)> + '_ {
+        let (block, lane) = self.places[index];
+        let block = &self.blocks[block];
+        let program = &self.programs[block.program];
+        let lanes = block.brains.len();
+
+        program
+            .outputs
+            .iter()
+            .zip(program.outputs())
+            .map(move |(output, neuron)| {
+                (
+                    *output,
+                    Response::cap(block.slots[neuron.slot * lanes + lane]),
+                )
+            })
+    }
+}
+
+impl Block {
+    fn tick<I: Copy, O>(
+        &mut self,
+        program: &Program<I, O>,
+        sense: &impl Fn(usize, &[I], &mut [Stimulus]),
+    ) {
+        let lanes = self.brains.len();
+        let row = |slot: usize| slot * lanes..(slot + 1) * lanes;
+
+        for (slot, memory) in &program.memories {
+            self.slots.copy_within(row(*slot), memory * lanes);
+        }
+
+        // Senses take the first slots
+        for (lane, brain) in self.brains.iter().enumerate() {
+            sense(*brain, &program.senses, &mut self.stimuli);
+            for (slot, stimulus) in self.stimuli.iter().enumerate() {
+                self.slots[slot * lanes + lane] = stimulus.as_f32();
+            }
+        }
+
+        let mut start = 0;
+        for (n, neuron) in program.neurons.iter().enumerate() {
+            self.sums.copy_from_slice(&self.biases[row(n)]);
+            for d in start..neuron.end {
+                let synapses = &self.synapses[row(d)];
+                let values = &self.slots[row(program.sources[d])];
+                for ((sum, synapse), value) in self.sums.iter_mut().zip(synapses).zip(values) {
+                    *sum += *synapse * *value;
+                }
+            }
+
+            for (value, sum) in self.slots[row(neuron.slot)].iter_mut().zip(&self.sums) {
+                *value = signal::activate(neuron.activation, *sum);
+            }
+            start = neuron.end;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Batches, LANES};
+    use crate::neural::test::evaluate;
+    use crate::neural::{Activation, Axon, Brain, Soma, Stimulus, Synapse};
+
+    #[test]
+    fn batched() {
+        // Brains alternate between a recurrent topology and a feed forward one, with the first
+        // spilling over a second block
+        let brain = |i: u16| -> Brain<u8, u8, 4> {
+            let weight = |offset: u16| Synapse::new(f32::from((i + offset) % 9) / 2. - 2.);
+            let recurrent = !i.is_multiple_of(3);
+            let axons = if recurrent {
+                vec![
+                    Axon::into_hidden(0, 0, weight(1)),
+                    Axon::inter_hidden(0, 1, weight(2)),
+                    Axon::inter_hidden(1, 0, weight(3)),
+                    Axon::from_hidden(1, 0, weight(4)),
+                    Axon::direct(1, 1, weight(5)),
+                ]
+            } else {
+                vec![
+                    Axon::direct(1, 0, weight(1)),
+                    Axon::into_hidden(0, 0, weight(2)),
+                    Axon::from_hidden(0, 1, weight(3)),
+                ]
+            };
+            // Activations are part of the topology, only biases differ
+            let soma = |activation, bias: u16| Soma {
+                activation,
+                bias: f32::from(bias % 5) / 4. - 0.5,
+            };
+            let hidden = if recurrent {
+                Activation::Gaussian
+            } else {
+                Activation::Relu
+            };
+            Brain::new(
+                axons.into_iter(),
+                |h| soma(hidden, i + u16::from(h)),
+                |o| soma(Activation::Tanh, i * 2 + u16::from(o)),
+            )
+        };
+        let input = |brain: usize, tick: usize, sense: u8| {
+            // ALLOWED: Small test indices
+            #[allow(clippy::cast_precision_loss)]
+            let value = ((brain * 7 + tick * 3 + usize::from(sense)) % 11) as f32 / 10.;
+            Stimulus::cap(value)
+        };
+
+        let brains: Vec<_> = (0..160).map(brain).collect();
+        let mut slots = vec![vec![]; brains.len()];
+        let mut batches = Batches::new((0..160).map(brain));
+        assert_eq!(batches.programs.len(), 2);
+        // 54 feed forward brains fit a block, the 106 recurrent ones take two
+        assert_eq!(batches.blocks.len(), 3);
+        assert_eq!(batches.blocks[1].brains.len(), LANES);
+
+        for tick in 0..5 {
+            batches.tick(|brain, senses, stimuli| {
+                for (stimulus, sense) in stimuli.iter_mut().zip(senses) {
+                    *stimulus = input(brain, tick, *sense);
+                }
+            });
+            for (index, brain) in brains.iter().enumerate() {
+                let alone = evaluate(brain, &mut slots[index], |sense| input(index, tick, sense));
+                let together: Vec<_> = batches.responses(index).collect();
+                assert_eq!(together, alone, "brain {index} on tick {tick}");
+            }
+        }
+    }
+}
@@ -0,0 +1,154 @@
+use super::neuron::{Dentrite, Hidden, Input, Neuron, Output, Ref, Sink, Source};
+use super::{Axon, Soma, Synapse};
+
+/// Neurons as wired by the axons, before being compiled into a [`Brain`](super::Brain)
+pub struct Graph<I: Copy + Eq, O: Copy + Eq> {
+    pub inputs: Vec<Input<I>>,
+    pub hiddens: Vec<Hidden>,
+    pub outputs: Vec<Output<O>>,
+}
+
+impl<I: Copy + Eq, O: Copy + Eq> Graph<I, O> {
+    /// Wires `axons`, with the soma of every hidden and output neuron picked by its index
+    pub fn new<const H: u8>(
+        axons: impl Iterator<Item = Axon<I, O, H>>,
+        hidden: impl Fn(u8) -> Soma,
+        output: impl Fn(O) -> Soma,
+    ) -> Self {
+        let mut inputs: Vec<Input<I>> = vec![];
+        let mut hiddens: Vec<Hidden> = vec![];
+        let mut outputs: Vec<Output<O>> = vec![];
+
+        for axon in axons {
+            match axon {
+                Axon::Direct {
+                    input,
+                    output,
+                    synapse,
+                } => {
+                    Self::make_synapse(input, output, synapse, &mut inputs, &mut outputs);
+                }
+                Axon::IntoHidden {
+                    input,
+                    output,
+                    synapse,
+                } => {
+                    Self::make_synapse(input, output, synapse, &mut inputs, &mut hiddens);
+                }
+                Axon::InterHidden {
+                    input,
+                    output,
+                    synapse,
+                } => {
+                    // Both ends live in the same list, so it must only be borrowed once at a time
+                    let input = Self::source(input, &mut hiddens);
+                    Self::connect(input, output, synapse, &mut hiddens);
+                }
+                Axon::FromHidden {
+                    input,
+                    output,
+                    synapse,
+                } => {
+                    Self::make_synapse(input, output, synapse, &mut hiddens, &mut outputs);
+                }
+            }
+        }
+
+        for neuron in &mut hiddens {
+            neuron.set_soma(hidden(neuron.index()));
+        }
+        for neuron in &mut outputs {
+            neuron.set_soma(output(neuron.index()));
+        }
+        Self::close_loops(&mut hiddens);
+
+        Self {
+            inputs,
+            hiddens,
+            outputs,
+        }
+    }
+
+    /// Marks the axons between hidden neurons that close a loop as recurrent
+    fn close_loops(hiddens: &mut [Hidden]) {
+        // `reaches[a][b]` if the signal of `a` flows into `b`
+        let mut reaches = vec![vec![false; hiddens.len()]; hiddens.len()];
+        for (b, hidden) in hiddens.iter().enumerate() {
+            for d in hidden.dentrites().filter(|d| d.neuron.hidden) {
+                reaches[d.neuron.index][b] = true;
+            }
+        }
+        for via in 0..hiddens.len() {
+            let onwards = reaches[via].clone();
+            for row in reaches.iter_mut().filter(|row| row[via]) {
+                for (reached, onward) in row.iter_mut().zip(&onwards) {
+                    *reached |= onward;
+                }
+            }
+        }
+
+        for (b, hidden) in hiddens.iter_mut().enumerate() {
+            for d in hidden
+                .dentrites_mut()
+                .iter_mut()
+                .filter(|d| d.neuron.hidden)
+            {
+                // The signal of `b` flows back into its own source
+                d.recurrent = reaches[b][d.neuron.index];
+            }
+        }
+    }
+
+    fn make_synapse<In: Copy + Eq, Out: Copy + Eq, NIn: Source<In>, NOut: Sink<Out>>(
+        input: In,
+        output: Out,
+        synapse: Synapse,
+        inputs: &mut Vec<NIn>,
+        outputs: &mut Vec<NOut>,
+    ) {
+        let input = Self::source(input, inputs);
+        Self::connect(input, output, synapse, outputs);
+    }
+
+    /// Reference to the `input` neuron, which is created if missing
+    fn source<In: Copy + Eq, NIn: Source<In>>(input: In, inputs: &mut Vec<NIn>) -> Ref {
+        let input_index = if let Some(idx) = inputs
+            .iter()
+            .enumerate()
+            .find(|(_, i)| i.index() == input)
+            .map(|(i, _)| i)
+        {
+            idx
+        } else {
+            inputs.push(NIn::new(input));
+            inputs.len() - 1
+        };
+
+        Ref {
+            hidden: NIn::hidden(),
+            index: input_index,
+        }
+    }
+
+    fn connect<Out: Copy + Eq, NOut: Sink<Out>>(
+        input: Ref,
+        output: Out,
+        synapse: Synapse,
+        outputs: &mut Vec<NOut>,
+    ) {
+        let dentrite = Dentrite {
+            neuron: input,
+            synapse,
+            recurrent: false,
+        };
+
+        if let Some(output) = outputs.iter_mut().find(|i| i.index() == output) {
+            // TODO: Can we precalculate what these two synapses would do to an input signal?
+            output.dentrites_mut().push(dentrite);
+        } else {
+            let mut output = NOut::new(output);
+            output.dentrites_mut().push(dentrite);
+            outputs.push(output);
+        }
+    }
+}
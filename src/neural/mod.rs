@@ -1,10 +1,18 @@
+mod batch;
+mod graph;
 mod neuron;
 mod signal;
 
-use neuron::{Dentrite, Hidden, Input, Neuron, Output, Ref, Sink, Source};
+pub use batch::Batches;
+use graph::Graph;
+use neuron::{Neuron, Sink};
+pub use signal::Activation;
 pub use signal::Amplifier as Synapse;
+pub use signal::Bipolar as Response;
 pub use signal::Signal as Stimulus;
+pub use signal::Soma;
 
+#[derive(Copy, Clone)]
 pub enum Axon<Input: Copy + Eq, Output: Copy + Eq, const H: u8> {
     Direct {
         input: Input,
@@ -62,145 +70,476 @@ impl<Input: Copy + Eq, Output: Copy + Eq, const H: u8> Axon<Input, Output, H> {
     }
 }
 
+/// Network of neurons evaluated once per tick
+///
+/// Axons between hidden neurons that close a loop are recurrent: they carry what their source
+/// computed on the previous tick, starting from 0, while every other axon carries the current
+/// tick. A hidden neuron thus only depends on the inputs and on the previous tick, so the outcome
+/// does not depend on the order neurons are evaluated in, and loops give the network a short-term
+/// memory
+///
+/// Every neuron adds its own bias to the sum of its inputs and activates it with its own
+/// [`Activation`], see [`Soma`], and outputs respond within -1..1
+///
+/// The wiring is compiled into a flat program: every tick reads the senses into the first slots,
+/// then computes the neurons in topological order from the slots of their dentrites. Neurons no
+/// output depends on are left out, so their senses are never read
 pub struct Brain<I: Copy + Eq, O: Copy + Eq, const H: u8> {
-    inputs: Vec<Input<I>>,
-    hiddens: Vec<Hidden>,
-    outputs: Vec<Output<O>>,
+    program: Program<I, O>,
+    /// Synapse of every dentrite, grouped by neuron
+    synapses: Vec<Synapse>,
+    /// Bias of every neuron
+    biases: Vec<f32>,
+}
+
+/// Topology of a compiled brain, which brains only differing by their weights share
+#[derive(PartialEq, Eq, Hash)]
+struct Program<I, O> {
+    /// Read into the first slots
+    senses: Vec<I>,
+    /// Hidden neurons in topological order, then outputs
+    neurons: Vec<Node>,
+    /// Slot read by every dentrite, grouped by neuron
+    sources: Vec<usize>,
+    /// Slots of the neurons read by a recurrent axon, copied to the slot of their memory before
+    /// a tick
+    memories: Vec<(usize, usize)>,
+    /// Computed into the slots following those of the hidden neurons
+    outputs: Vec<O>,
+    /// Number of slots
+    slots: usize,
+}
+
+impl<I, O> Program<I, O> {
+    /// Neurons computing the outputs, in the same order
+    fn outputs(&self) -> &[Node] {
+        &self.neurons[self.neurons.len() - self.outputs.len()..]
+    }
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
+struct Node {
+    slot: usize,
+    activation: Activation,
+    /// Past the last dentrite of the neuron, the first being past the last of the previous one
+    end: usize,
 }
 
 impl<I: Copy + Eq, O: Copy + Eq, const H: u8> Brain<I, O, H> {
-    pub fn new(axons: impl Iterator<Item = Axon<I, O, H>>) -> Self {
-        let mut inputs: Vec<Input<I>> = vec![];
-        let mut hiddens: Vec<Hidden> = vec![];
-        let mut outputs: Vec<Output<O>> = vec![];
-
-        for axon in axons {
-            match axon {
-                Axon::Direct {
-                    input,
-                    output,
-                    synapse,
-                } => {
-                    Self::make_synapse(input, output, synapse, &mut inputs, &mut outputs);
-                }
-                Axon::IntoHidden {
-                    input,
-                    output,
-                    synapse,
-                } => {
-                    Self::make_synapse(input, output, synapse, &mut inputs, &mut hiddens);
-                }
-                Axon::InterHidden {
-                    input,
-                    output,
-                    synapse,
-                } => {
-                    let hiddens: *mut Vec<Hidden> = &raw mut hiddens;
-                    Self::make_synapse(input, output, synapse, unsafe { &mut *hiddens }, unsafe {
-                        &mut *hiddens
-                    });
-                }
-                Axon::FromHidden {
-                    input,
-                    output,
-                    synapse,
-                } => {
-                    Self::make_synapse(input, output, synapse, &mut hiddens, &mut outputs);
-                }
+    /// Wires `axons`, with the soma of every hidden and output neuron picked by its index
+    pub fn new(
+        axons: impl Iterator<Item = Axon<I, O, H>>,
+        hidden: impl Fn(u8) -> Soma,
+        output: impl Fn(O) -> Soma,
+    ) -> Self {
+        Self::compile(&Graph::new(axons, hidden, output))
+    }
+
+    fn compile(graph: &Graph<I, O>) -> Self {
+        let order = Self::order(graph);
+
+        let mut slots = 0;
+        let mut slot = || {
+            slots += 1;
+            slots - 1
+        };
+
+        let mut senses = vec![];
+        let mut sense_slots = vec![None; graph.inputs.len()];
+        let dentrites = order
+            .iter()
+            .flat_map(|index| graph.hiddens[*index].dentrites())
+            .chain(graph.outputs.iter().flat_map(Sink::dentrites));
+        for d in dentrites.filter(|d| !d.neuron.hidden) {
+            if sense_slots[d.neuron.index].is_none() {
+                sense_slots[d.neuron.index] = Some(slot());
+                senses.push(graph.inputs[d.neuron.index].index());
+            }
+        }
+
+        let mut hidden_slots = vec![0; graph.hiddens.len()];
+        for index in &order {
+            hidden_slots[*index] = slot();
+        }
+        let outputs = graph.outputs.iter().map(Neuron::index).collect();
+        let output_slots: Vec<_> = graph.outputs.iter().map(|_| slot()).collect();
+
+        let mut memory_slots = vec![None; graph.hiddens.len()];
+        let mut memories = vec![];
+        let recurrent = order
+            .iter()
+            .flat_map(|index| graph.hiddens[*index].dentrites())
+            .filter(|d| d.recurrent);
+        for d in recurrent {
+            if memory_slots[d.neuron.index].is_none() {
+                let memory = slot();
+                memory_slots[d.neuron.index] = Some(memory);
+                memories.push((hidden_slots[d.neuron.index], memory));
+            }
+        }
+
+        let mut neurons = vec![];
+        let mut sources = vec![];
+        let mut synapses = vec![];
+        let mut biases = vec![];
+        let sinks = order
+            .iter()
+            .map(|index| {
+                let neuron = &graph.hiddens[*index];
+                (hidden_slots[*index], neuron.soma(), neuron.dentrites())
+            })
+            .chain(
+                graph
+                    .outputs
+                    .iter()
+                    .zip(&output_slots)
+                    .map(|(neuron, slot)| (*slot, neuron.soma(), neuron.dentrites())),
+            );
+        for (slot, soma, dentrites) in sinks {
+            for d in dentrites {
+                sources.push(
+                    match (d.neuron.hidden, d.recurrent) {
+                        (false, _) => sense_slots[d.neuron.index],
+                        (true, false) => Some(hidden_slots[d.neuron.index]),
+                        (true, true) => memory_slots[d.neuron.index],
+                    }
+                    .unwrap_or_default(),
+                );
+                synapses.push(d.synapse);
             }
+            neurons.push(Node {
+                slot,
+                activation: soma.activation,
+                end: sources.len(),
+            });
+            biases.push(soma.bias);
         }
 
         Self {
-            inputs,
-            hiddens,
-            outputs,
+            program: Program {
+                senses,
+                neurons,
+                sources,
+                memories,
+                outputs,
+                slots,
+            },
+            synapses,
+            biases,
         }
     }
 
-    fn make_synapse<In: Copy + Eq, Out: Copy + Eq, NIn: Source<In>, NOut: Sink<Out>>(
-        input: In,
-        output: Out,
-        synapse: Synapse,
-        inputs: &mut Vec<NIn>,
-        outputs: &mut Vec<NOut>,
-    ) {
-        let input_index = if let Some(idx) = inputs
+    /// Hidden neurons some output depends on, if only on the next tick, in topological order
+    fn order(graph: &Graph<I, O>) -> Vec<usize> {
+        let mut live = vec![false; graph.hiddens.len()];
+        let mut pending: Vec<usize> = graph
+            .outputs
             .iter()
-            .enumerate()
-            .find(|(_, i)| i.index() == input)
-            .map(|(i, _)| i)
-        {
-            idx
-        } else {
-            inputs.push(NIn::new(input));
-            inputs.len() - 1
-        };
+            .flat_map(Sink::dentrites)
+            .filter(|d| d.neuron.hidden)
+            .map(|d| d.neuron.index)
+            .collect();
+        while let Some(index) = pending.pop() {
+            if !live[index] {
+                live[index] = true;
+                pending.extend(
+                    graph.hiddens[index]
+                        .dentrites()
+                        .filter(|d| d.neuron.hidden)
+                        .map(|d| d.neuron.index),
+                );
+            }
+        }
 
-        let dentrite = Dentrite {
-            neuron: Ref {
-                hidden: NIn::hidden(),
-                index: input_index,
-            },
-            synapse,
+        let mut order = Vec::with_capacity(graph.hiddens.len());
+        let mut visited = vec![false; graph.hiddens.len()];
+        for index in (0..graph.hiddens.len()).filter(|i| live[*i]) {
+            Self::sort(graph, index, &mut visited, &mut order);
+        }
+        order
+    }
+
+    /// Appends the hidden neuron at `index` to `order` after every one it depends on this tick
+    fn sort(graph: &Graph<I, O>, index: usize, visited: &mut [bool], order: &mut Vec<usize>) {
+        if visited[index] {
+            return;
+        }
+        visited[index] = true;
+
+        // Recurrent axons are what keeps this acyclic
+        for d in graph.hiddens[index].dentrites() {
+            if d.neuron.hidden && !d.recurrent {
+                Self::sort(graph, d.neuron.index, visited, order);
+            }
+        }
+        order.push(index);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::neuron::{Neuron, Ref, Sink};
+    use super::{signal, Activation, Axon, Brain, Graph, Response, Soma, Stimulus, Synapse};
+
+    /// A brain and the slots it is evaluated on, see [`evaluate`]
+    type Alone = (Brain<u8, u8, 4>, Vec<f32>);
+
+    /// Sums `inputs` with the bias of `soma` and activates the result
+    fn aggregate(inputs: impl Iterator<Item = f32>, soma: Soma) -> f32 {
+        signal::activate(soma.activation, inputs.fold(soma.bias, |a, c| a + c))
+    }
+
+    /// Runs the program of `brain` alone, the way [`super::Batches`] runs it on every lane
+    ///
+    /// `slots` hold the value of every sense and neuron, then of every memory, from one tick to
+    /// the next
+    pub(super) fn evaluate<I: Copy + Eq, O: Copy + Eq, const H: u8>(
+        brain: &Brain<I, O, H>,
+        slots: &mut Vec<f32>,
+        input: impl Fn(I) -> Stimulus,
+    ) -> Vec<(O, Response)> {
+        let program = &brain.program;
+        slots.resize(program.slots, 0.);
+
+        for (slot, memory) in &program.memories {
+            slots[*memory] = slots[*slot];
+        }
+        for (slot, sense) in slots.iter_mut().zip(&program.senses) {
+            *slot = input(*sense).as_f32();
+        }
+
+        let mut start = 0;
+        for (neuron, bias) in program.neurons.iter().zip(&brain.biases) {
+            let dentrites = brain.synapses[start..neuron.end]
+                .iter()
+                .zip(&program.sources[start..neuron.end]);
+            slots[neuron.slot] = aggregate(
+                dentrites.map(|(synapse, source)| *synapse * slots[*source]),
+                Soma {
+                    activation: neuron.activation,
+                    bias: *bias,
+                },
+            );
+            start = neuron.end;
+        }
+
+        program
+            .outputs
+            .iter()
+            .zip(program.outputs())
+            .map(|(output, neuron)| (*output, Response::cap(slots[neuron.slot])))
+            .collect()
+    }
+
+    fn brain(
+        axons: impl IntoIterator<Item = Axon<u8, u8, 4>>,
+        hidden: Activation,
+        output: Soma,
+    ) -> Alone {
+        let hidden = Soma {
+            activation: hidden,
+            bias: 0.,
         };
+        (
+            Brain::new(axons.into_iter(), |_| hidden, |_| output),
+            vec![],
+        )
+    }
 
-        if let Some(output) = outputs.iter_mut().find(|i| i.index() == output) {
-            // TODO: Can we precalculate what these two synapses would do to an input signal?
-            output.dentrites_mut().push(dentrite);
-        } else {
-            let mut output = NOut::new(output);
-            output.dentrites_mut().push(dentrite);
-            outputs.push(output);
+    fn tanh(axons: Vec<Axon<u8, u8, 4>>) -> Alone {
+        brain(axons, Activation::Tanh, Soma::default())
+    }
+
+    fn unbiased(activation: Activation) -> Soma {
+        Soma {
+            activation,
+            bias: 0.,
         }
     }
 
-    pub fn stimuli(&mut self, input: impl Copy + Fn(I) -> Stimulus) -> Vec<(O, Stimulus)> {
-        self.clear_visits();
+    fn tick((brain, slots): &mut Alone, input: f32) -> f32 {
+        evaluate(brain, slots, |_| Stimulus::cap(input))
+            .first()
+            .map_or(0., |(_, stimulus)| stimulus.as_f32())
+    }
+
+    #[test]
+    fn memory() {
+        // A hidden neuron feeding itself keeps a pulse alive after the input went quiet
+        let mut brain = tanh(vec![
+            Axon::into_hidden(0, 0, Synapse::new(1.)),
+            Axon::inter_hidden(0, 0, Synapse::new(2.)),
+            Axon::from_hidden(0, 0, Synapse::new(4.)),
+        ]);
+
+        assert!(tick(&mut brain, 0.) < f32::EPSILON);
+        assert!(tick(&mut brain, 1.) > 0.9);
+        assert!(tick(&mut brain, 0.) > 0.9);
+    }
 
-        let mut output = Vec::with_capacity(self.outputs.len());
+    #[test]
+    fn loop_order() {
+        // Two neurons feeding each other read the previous tick whichever comes first
+        let axons = |reversed: bool| {
+            let mut axons = vec![
+                Axon::into_hidden(0, 0, Synapse::new(1.)),
+                Axon::inter_hidden(0, 1, Synapse::new(1.)),
+                Axon::inter_hidden(1, 0, Synapse::new(-1.)),
+                Axon::from_hidden(0, 0, Synapse::new(1.)),
+                Axon::from_hidden(1, 0, Synapse::new(1.)),
+            ];
+            if reversed {
+                axons.reverse();
+            }
+            tanh(axons)
+        };
+        let (mut forward, mut backward) = (axons(false), axons(true));
 
-        for o in &self.outputs {
-            let signal = signal::aggregate(o.dentrites().map(|d| {
-                d.synapse * Self::update(&mut self.inputs, &mut self.hiddens, d.neuron, input)
-            }));
-            output.push((o.index(), Stimulus::cap(signal)));
+        for input in [1., 0.5, 0., 1.] {
+            let (a, b) = (tick(&mut forward, input), tick(&mut backward, input));
+            assert!((a - b).abs() < f32::EPSILON, "{a} != {b}");
         }
+    }
+
+    #[test]
+    fn activations() {
+        let axons = || {
+            [
+                Axon::into_hidden(0, 0, Synapse::new(1.)),
+                Axon::from_hidden(0, 0, Synapse::new(-0.5)),
+            ]
+        };
 
-        output
+        // A single output responds both ways
+        let mut identity = brain(axons(), Activation::Step, unbiased(Activation::Identity));
+        assert!((tick(&mut identity, 0.25) + 0.5).abs() < f32::EPSILON);
+        assert!(tick(&mut identity, 0.).abs() < f32::EPSILON);
+
+        let mut relu = brain(axons(), Activation::Identity, unbiased(Activation::Relu));
+        assert!(tick(&mut relu, 1.).abs() < f32::EPSILON);
+
+        let mut gaussian = brain(axons(), Activation::Sigmoid, unbiased(Activation::Gaussian));
+        let expected = (-(0.5_f32 / (1. + (-1_f32).exp())).powi(2)).exp();
+        assert!((tick(&mut gaussian, 1.) - expected).abs() < 1e-6);
     }
 
-    fn clear_visits(&mut self) {
-        self.inputs.iter_mut().for_each(Source::unvisit);
-        self.hiddens.iter_mut().for_each(Source::unvisit);
+    #[test]
+    fn bias() {
+        let output = Soma {
+            activation: Activation::Identity,
+            bias: -0.5,
+        };
+        let mut biased = brain(
+            [Axon::direct(0, 0, Synapse::new(1.))],
+            Activation::Tanh,
+            output,
+        );
+        assert!((tick(&mut biased, 0.) + 0.5).abs() < f32::EPSILON);
+        assert!((tick(&mut biased, 1.) - 0.5).abs() < f32::EPSILON);
+
+        // Unbounded activations are kept within range
+        let output = Soma {
+            activation: Activation::Identity,
+            bias: 3.,
+        };
+        let mut saturated = brain(
+            [Axon::direct(0, 0, Synapse::new(1.))],
+            Activation::Tanh,
+            output,
+        );
+        assert!((tick(&mut saturated, 1.) - 1.).abs() < f32::EPSILON);
     }
 
-    fn update(
-        inputs: &mut Vec<Input<I>>,
-        hiddens: &mut Vec<Hidden>,
-        neuron_ref: Ref,
-        input: impl Copy + Fn(I) -> Stimulus,
-    ) -> f32 {
-        if neuron_ref.hidden {
-            // SAFETY: References are never out of bounds
-            let neuron: *mut Hidden = unsafe { hiddens.get_unchecked_mut(neuron_ref.index) };
-            // SAFETY: Safe because we never modify the list nor do we revisit a node
-            let neuron = unsafe { &mut *neuron };
+    /// What the recursive evaluator the brain used to walk computes for an acyclic `graph`
+    fn interpret(graph: &Graph<u8, u8>, input: &impl Fn(u8) -> Stimulus) -> Vec<(u8, Response)> {
+        fn value(
+            graph: &Graph<u8, u8>,
+            neuron: Ref,
+            input: &impl Fn(u8) -> Stimulus,
+            latched: &mut Vec<Option<f32>>,
+        ) -> f32 {
+            if !neuron.hidden {
+                return input(graph.inputs[neuron.index].index()).as_f32();
+            }
+            if let Some(latch) = latched[neuron.index] {
+                return latch;
+            }
 
-            if neuron.visit() {
-                neuron.latch(signal::aggregate(
-                    neuron
-                        .dentrites()
-                        .map(|d| d.synapse * Self::update(inputs, hiddens, d.neuron, input)),
-                ));
+            let hidden = &graph.hiddens[neuron.index];
+            let latch = aggregate(
+                hidden
+                    .dentrites()
+                    .map(|d| d.synapse * value(graph, d.neuron, input, latched)),
+                hidden.soma(),
+            );
+            latched[neuron.index] = Some(latch);
+            latch
+        }
+
+        let mut latched = vec![None; graph.hiddens.len()];
+        graph
+            .outputs
+            .iter()
+            .map(|o| {
+                let signal = aggregate(
+                    o.dentrites()
+                        .map(|d| d.synapse * value(graph, d.neuron, input, &mut latched)),
+                    o.soma(),
+                );
+                (o.index(), Response::cap(signal))
+            })
+            .collect()
+    }
+
+    #[test]
+    fn compiled() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        for _ in 0..64 {
+            let mut axons: Vec<Axon<u8, u8, 8>> = vec![];
+            for _ in 0..rng.gen_range(1..24) {
+                let synapse = Synapse::new(rng.gen_range(-4.0..4.0));
+                let (input, output) = (rng.gen_range(0..8), rng.gen_range(0..8));
+                axons.push(match rng.gen_range(0..4) {
+                    0 => Axon::direct(input, output % 4, synapse),
+                    1 => Axon::into_hidden(input, output, synapse),
+                    // Only wired forward so that the network stays acyclic
+                    2 => {
+                        let from = input % 7;
+                        Axon::inter_hidden(from, rng.gen_range(from + 1..8), synapse)
+                    }
+                    _ => Axon::from_hidden(input, output % 4, synapse),
+                });
             }
-            neuron.latched()
-        } else {
-            // SAFETY: References are never out of bounds
-            let neuron = unsafe { inputs.get_unchecked_mut(neuron_ref.index) };
-            if neuron.visit() {
-                neuron.latch(input(neuron.index()).as_f32());
+            let somas: Vec<_> = (0..12)
+                .map(|_| Soma {
+                    activation: Activation::ALL[rng.gen_range(0..Activation::ALL.len())],
+                    bias: rng.gen_range(-2.0..2.0),
+                })
+                .collect();
+            let graph = || {
+                Graph::new(
+                    axons.iter().copied(),
+                    |h| somas[usize::from(h)],
+                    |o| somas[8 + usize::from(o)],
+                )
+            };
+
+            let reference = graph();
+            assert!(reference
+                .hiddens
+                .iter()
+                .flat_map(Sink::dentrites)
+                .all(|d| !d.recurrent));
+            let brain = Brain::<u8, u8, 8>::compile(&graph());
+            let mut slots = vec![];
+
+            for tick in 0..4 {
+                let input = |i: u8| Stimulus::cap(f32::from(i * 3 + tick) % 7. / 6.);
+                let compiled = evaluate(&brain, &mut slots, input);
+                assert_eq!(compiled, interpret(&reference, &input));
             }
-            neuron.latched()
         }
     }
 }
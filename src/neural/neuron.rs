@@ -1,4 +1,4 @@
-use super::Synapse;
+use super::{Soma, Synapse};
 
 pub trait Neuron<I: Copy + Eq> {
     fn new(index: I) -> Self;
@@ -7,15 +7,13 @@ pub trait Neuron<I: Copy + Eq> {
 
 pub trait Source<I: Copy + Eq>: Neuron<I> {
     fn hidden() -> bool;
-    fn visit(&mut self) -> bool;
-    fn unvisit(&mut self);
-    fn latched(&self) -> f32;
-    fn latch(&mut self, latch: f32);
 }
 
 pub trait Sink<I: Copy + Eq>: Neuron<I> {
     fn dentrites(&self) -> std::slice::Iter<'_, Dentrite>;
     fn dentrites_mut(&mut self) -> &mut Vec<Dentrite>;
+    fn soma(&self) -> Soma;
+    fn set_soma(&mut self, soma: Soma);
 }
 
 #[derive(Copy, Clone, Eq, PartialEq)]
@@ -27,21 +25,17 @@ pub struct Ref {
 pub struct Dentrite {
     pub neuron: Ref,
     pub synapse: Synapse,
+    /// Reads what the neuron computed on the previous tick, as it closes a loop
+    pub recurrent: bool,
 }
 
 pub struct Input<I: Copy + Eq> {
     index: I,
-    latch: f32,
-    visited: bool,
 }
 
 impl<I: Copy + Eq> Neuron<I> for Input<I> {
     fn new(index: I) -> Self {
-        Self {
-            index,
-            latch: 0.0,
-            visited: false,
-        }
+        Self { index }
     }
 
     #[inline]
@@ -55,38 +49,12 @@ impl<I: Copy + Eq> Source<I> for Input<I> {
     fn hidden() -> bool {
         false
     }
-
-    #[inline]
-    fn visit(&mut self) -> bool {
-        if self.visited {
-            false
-        } else {
-            self.visited = true;
-            true
-        }
-    }
-
-    #[inline]
-    fn unvisit(&mut self) {
-        self.visited = false;
-    }
-
-    #[inline]
-    fn latched(&self) -> f32 {
-        self.latch
-    }
-
-    #[inline]
-    fn latch(&mut self, latch: f32) {
-        self.latch = latch;
-    }
 }
 
 pub struct Hidden {
     index: u8,
     dentrites: Vec<Dentrite>,
-    latch: f32,
-    visited: bool,
+    soma: Soma,
 }
 
 impl Neuron<u8> for Hidden {
@@ -94,8 +62,7 @@ impl Neuron<u8> for Hidden {
         Self {
             index,
             dentrites: vec![],
-            latch: 0.0,
-            visited: false,
+            soma: Soma::default(),
         }
     }
 
@@ -110,31 +77,6 @@ impl Source<u8> for Hidden {
     fn hidden() -> bool {
         true
     }
-
-    #[inline]
-    fn visit(&mut self) -> bool {
-        if self.visited {
-            false
-        } else {
-            self.visited = true;
-            true
-        }
-    }
-
-    #[inline]
-    fn unvisit(&mut self) {
-        self.visited = false;
-    }
-
-    #[inline]
-    fn latched(&self) -> f32 {
-        self.latch
-    }
-
-    #[inline]
-    fn latch(&mut self, latch: f32) {
-        self.latch = latch;
-    }
 }
 
 impl Sink<u8> for Hidden {
@@ -147,11 +89,22 @@ impl Sink<u8> for Hidden {
     fn dentrites_mut(&mut self) -> &mut Vec<Dentrite> {
         &mut self.dentrites
     }
+
+    #[inline]
+    fn soma(&self) -> Soma {
+        self.soma
+    }
+
+    #[inline]
+    fn set_soma(&mut self, soma: Soma) {
+        self.soma = soma;
+    }
 }
 
 pub struct Output<I: Copy + Eq> {
     index: I,
     dentrites: Vec<Dentrite>,
+    soma: Soma,
 }
 
 impl<I: Copy + Eq> Neuron<I> for Output<I> {
@@ -159,6 +112,7 @@ impl<I: Copy + Eq> Neuron<I> for Output<I> {
         Self {
             index,
             dentrites: vec![],
+            soma: Soma::default(),
         }
     }
 
@@ -178,4 +132,14 @@ impl<I: Copy + Eq> Sink<I> for Output<I> {
     fn dentrites_mut(&mut self) -> &mut Vec<Dentrite> {
         &mut self.dentrites
     }
+
+    #[inline]
+    fn soma(&self) -> Soma {
+        self.soma
+    }
+
+    #[inline]
+    fn set_soma(&mut self, soma: Soma) {
+        self.soma = soma;
+    }
 }
@@ -44,6 +44,76 @@ impl std::ops::Mul<f32> for Amplifier {
     }
 }
 
-pub fn aggregate(inputs: impl Iterator<Item = f32>) -> f32 {
-    inputs.fold(0.0, |a, c| a + c).tanh()
+/// Float capped to the -1..1 range
+#[derive(Copy, Clone, PartialEq, PartialOrd, Debug)]
+pub struct Bipolar(f32);
+
+impl Bipolar {
+    pub fn cap(value: f32) -> Self {
+        Self(value.clamp(-1., 1.))
+    }
+
+    #[inline]
+    pub fn as_f32(self) -> f32 {
+        self.0
+    }
+}
+
+/// Function a neuron applies to the sum of its inputs
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Activation {
+    #[default]
+    Tanh,
+    Sigmoid,
+    Relu,
+    /// 1 for a positive sum, 0 otherwise
+    Step,
+    Identity,
+    /// Bell curve peaking at 1 for a sum of 0
+    Gaussian,
+}
+
+impl Activation {
+    pub const ALL: [Self; 6] = [
+        Self::Tanh,
+        Self::Sigmoid,
+        Self::Relu,
+        Self::Step,
+        Self::Identity,
+        Self::Gaussian,
+    ];
+
+    #[inline]
+    fn apply(self, sum: f32) -> f32 {
+        match self {
+            Self::Tanh => sum.tanh(),
+            Self::Sigmoid => 1. / (1. + (-sum).exp()),
+            Self::Relu => sum.max(0.),
+            Self::Step => {
+                if sum > 0. {
+                    1.
+                } else {
+                    0.
+                }
+            }
+            Self::Identity => sum,
+            Self::Gaussian => (-sum * sum).exp(),
+        }
+    }
+}
+
+/// What a neuron makes of its inputs
+#[derive(Copy, Clone, PartialEq, Debug, Default)]
+pub struct Soma {
+    pub activation: Activation,
+    /// Added to the sum of the inputs before activating
+    pub bias: f32,
+}
+
+/// Activates `sum`, keeping the result within -1..1 like that of `tanh` so that loops of unbounded
+/// activations cannot blow up
+#[inline]
+pub fn activate(activation: Activation, sum: f32) -> f32 {
+    activation.apply(sum).clamp(-1., 1.)
 }
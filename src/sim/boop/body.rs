@@ -1,13 +1,31 @@
+use super::super::snapshot::Writer;
 use super::super::Direction;
+use super::traits::Traits;
+use super::Intent;
 
 pub struct Body {
     direction: Direction,
+    speed: f32,
+    turn_rate: f32,
+    size: f32,
+    sensing: f32,
+    colour: u32,
 }
 
 impl Body {
-    pub fn new() -> Self {
+    pub fn new(traits: Traits, rng: &mut impl rand::Rng) -> Self {
+        Self::with(Direction::random(rng), traits)
+    }
+
+    /// Body facing `direction`, as expressed from `traits`
+    pub fn with(direction: Direction, traits: Traits) -> Self {
         Self {
-            direction: Direction::random(),
+            direction,
+            speed: traits.speed(),
+            turn_rate: traits.turn_rate(),
+            size: traits.size(),
+            sensing: traits.sensing(),
+            colour: traits.colour(),
         }
     }
 
@@ -16,13 +34,42 @@ impl Body {
         self.direction
     }
 
+    /// Reach for food, and weight to carry around
     #[inline]
-    pub fn turn_right(&mut self, amount: f32) {
-        self.direction += amount;
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    /// Range of the senses, as a factor
+    #[inline]
+    pub fn sensing(&self) -> f32 {
+        self.sensing
+    }
+
+    /// As `0xRRGGBB`
+    #[inline]
+    pub fn colour(&self) -> u32 {
+        self.colour
+    }
+
+    /// Scales what the brain decided to what the body can do
+    #[inline]
+    pub fn perform(&self, intent: Intent) -> Intent {
+        Intent {
+            turn: intent.turn * self.turn_rate,
+            steering: intent.steering * self.turn_rate,
+            advance: intent.advance * self.speed,
+        }
     }
 
+    /// Turns clockwise, or counter-clockwise for a negative `amount`
     #[inline]
-    pub fn turn_left(&mut self, amount: f32) {
-        self.direction -= amount;
+    pub fn turn(&mut self, amount: f32) {
+        self.direction += amount;
+    }
+
+    /// Only the direction is written, the rest is expressed from the genome again when loading
+    pub fn save(&self, writer: &mut impl Writer) -> std::io::Result<()> {
+        self.direction.save(writer)
     }
 }
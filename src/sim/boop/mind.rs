@@ -1,92 +1,312 @@
-use super::super::{Index, Simulation};
-use crate::neural::{Stimulus, Synapse};
-
-type Brain = super::super::super::neural::Brain<Input, Output, 8>;
-type Axon = super::super::super::neural::Axon<Input, Output, 8>;
-
-pub struct Mind {
-    brain: Brain,
-    genome: Genome,
+use super::super::crossover::Crossover;
+use super::super::mutation::{Kind, Mutation, Mutations};
+use super::super::snapshot::{Reader, Writer};
+use super::super::world::{Neighbours, World};
+use super::super::{Direction, Index};
+use super::body::Body;
+use super::neurons::Neurons;
+use super::traits::Traits;
+use super::{Boop, Intent};
+use crate::neural::{Activation, Response, Soma, Stimulus, Synapse};
+
+/// Hidden neuron indices wrap around past this count
+pub const MAX_HIDDEN_NEURONS: u8 = 8;
+
+/// Number of output neurons
+pub const OUTPUTS: u8 = Output::COUNT;
+
+/// How far a boop with neutral sensing can see walls and other boops
+const SIGHT: f32 = 8.;
+
+/// Number of boops in sight that makes for a full crowd
+const CROWD: f32 = 8.;
+
+type Brain = super::super::super::neural::Brain<Input, Output, MAX_HIDDEN_NEURONS>;
+type Axon = super::super::super::neural::Axon<Input, Output, MAX_HIDDEN_NEURONS>;
+type Batches = super::super::super::neural::Batches<Input, Output, MAX_HIDDEN_NEURONS>;
+
+/// Activation functions the neurons use
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Neural {
+    /// Used by every neuron, unless evolved
+    pub activation: Activation,
+    /// Hidden neurons use the activation function picked by their genes instead
+    pub evolved: bool,
 }
 
-impl Mind {
-    pub fn random(synapses: u16, hidden_neurons: u8) -> Self {
-        let genome = Genome::random(synapses, hidden_neurons);
-        Self {
-            brain: genome.build(),
-            genome,
-        }
-    }
+/// Brains of every boop of a generation, evaluated together
+///
+/// Boops of a converged population share their wiring, see [`Batches`]
+pub struct Minds(Batches);
 
-    #[inline]
-    pub fn from(genome: Genome) -> Self {
-        Self {
-            brain: genome.build(),
-            genome,
-        }
+impl Minds {
+    /// Wires the brain of every genome, in the order of the boops
+    pub fn new<'a>(genomes: impl Iterator<Item = &'a Genome>, neural: Neural) -> Self {
+        Self(Batches::new(genomes.map(|genome| genome.build(neural))))
     }
 
-    #[inline]
-    pub fn react(&mut self, simulation: &mut Simulation, index: Index) {
-        self.brain
-            .stimuli(|input| input.sense(simulation, index))
-            .into_iter()
-            .filter_map(|(out, stim)| out.spike(stim))
-            .for_each(|(out, stim)| out.act(simulation, index, stim));
+    /// Decides what every boop wants to do, without touching the world
+    ///
+    /// Dead boops sense nothing and do nothing
+    pub fn react(
+        &mut self,
+        boops: &[Boop],
+        world: &World,
+        noise: &[f32],
+        intents: &mut Vec<Intent>,
+    ) {
+        self.0.tick(|i, inputs, stimuli| {
+            let index = Index(i);
+            if !world.alive(index) {
+                stimuli.fill(Stimulus::from(false));
+                return;
+            }
+
+            let neighbours = std::cell::OnceCell::new();
+            for (input, stimulus) in inputs.iter().zip(stimuli) {
+                *stimulus = input.sense(&boops[i], world, index, noise[i], &neighbours);
+            }
+        });
+
+        intents.clear();
+        intents.extend(boops.iter().enumerate().map(|(i, boop)| {
+            if world.alive(Index(i)) {
+                boop.perform(self.intent(i))
+            } else {
+                Intent::default()
+            }
+        }));
     }
 
-    #[inline]
-    pub fn genome(&self) -> &Genome {
-        &self.genome
+    /// What the brain of the boop at `index` decided on the last tick
+    fn intent(&self, index: usize) -> Intent {
+        let mut intent = Intent::default();
+        self.0
+            .responses(index)
+            .filter_map(|(out, response)| out.spike(response))
+            .for_each(|(out, response)| out.act(&mut intent, response));
+        intent
     }
 }
 
-pub struct Genome(Vec<Gene>);
+/// Neural genes wiring the brain, the traits of the body and the genes of the neurons
+#[derive(Clone)]
+pub struct Genome(Vec<Gene>, Traits, Neurons);
 
 impl Genome {
-    fn random(synapses: u16, hidden_neurons: u8) -> Self {
-        Self(build_vec!(
-            || Gene::new(rand::random(), hidden_neurons),
+    pub fn random(synapses: u16, hidden_neurons: u8, rng: &mut impl rand::Rng) -> Self {
+        Self::neutral(build_vec!(
+            || Gene::new(rng.gen(), hidden_neurons),
             synapses
         ))
     }
 
+    /// Neural genes with a body and neurons that never evolved, see [`Traits::neutral`] and
+    /// [`Neurons::neutral`]
+    fn neutral(genes: Vec<Gene>) -> Self {
+        let signature = genes.iter().fold(0, |a, c| a ^ c.0);
+        Self(genes, Traits::neutral(signature), Neurons::neutral())
+    }
+
+    /// Fingerprint of the neural genes
     #[inline]
     pub fn signature(&self) -> u32 {
         self.0.iter().fold(0, |a, c| a ^ c.0)
     }
 
-    pub fn combine(&self, other: &Self) -> Self {
+    #[inline]
+    pub fn traits(&self) -> Traits {
+        self.1
+    }
+
+    /// Number of genes
+    pub fn length(&self) -> usize {
+        self.0.len()
+    }
+
+    /// Synapse weight of every gene
+    pub fn weights(&self) -> impl Iterator<Item = f32> + '_ {
+        self.0.iter().map(|g| g.weight())
+    }
+
+    /// Number of genes building each kind of [`Axon`]: direct, into hidden, inter hidden and
+    /// from hidden
+    pub fn axon_kinds(&self) -> [usize; 4] {
+        let mut kinds = [0; 4];
+        for gene in &self.0 {
+            let (conn_type, _, _) = Gene::dissect(gene.0);
+            kinds[usize::from(conn_type)] += 1;
+        }
+        kinds
+    }
+
+    pub fn combine(&self, other: &Self, crossover: &Crossover, rng: &mut impl rand::Rng) -> Self {
+        use rand::seq::SliceRandom;
+
+        let mut genes = crossover.combine(&self.0, &other.0, Gene::wiring, rng);
+
+        // An offspring always keeps at least one gene
+        if genes.is_empty() {
+            genes.extend(
+                self.0
+                    .iter()
+                    .chain(&other.0)
+                    .collect::<Vec<_>>()
+                    .choose(rng)
+                    .copied(),
+            );
+        }
+
         Self(
-            self.0
-                .iter()
-                .zip(other.0.iter())
-                .map(|(f, m)| if rand::random() { f } else { m })
-                .copied()
-                .collect(),
+            genes,
+            self.1.combine(other.1, rng),
+            self.2.combine(other.2, rng),
         )
     }
 
-    pub fn mutate(&mut self, hidden_neurons: u8) {
-        use rand::seq::SliceRandom;
+    pub fn mutate(
+        &mut self,
+        mutation: &Mutation,
+        hidden_neurons: u8,
+        rng: &mut impl rand::Rng,
+    ) -> Mutations {
+        let mut mutations = Mutations::default();
+
+        if !self.0.is_empty() && mutation.limit != Some(0) && rng.gen::<f32>() < mutation.rate {
+            let index = rng.gen_range(0..self.0.len());
+            let (gene, kind) = self.0[index].point(mutation, hidden_neurons, rng);
+            self.0[index] = gene;
+            mutations.count(kind);
+        }
+
+        if mutation.gene_rate > 0. {
+            for gene in &mut self.0 {
+                if mutation.limit.is_some_and(|l| mutations.total() >= l) {
+                    break;
+                }
+
+                if rng.gen::<f32>() < mutation.gene_rate {
+                    let (mutated, kind) = gene.point(mutation, hidden_neurons, rng);
+                    *gene = mutated;
+                    mutations.count(kind);
+                }
+            }
+        }
+
+        if !self.0.is_empty() && rng.gen::<f32>() < mutation.duplication {
+            let index = rng.gen_range(0..self.0.len());
+            self.0.insert(index, self.0[index]);
+            mutations.duplications += 1;
+        }
+
+        if self.0.len() > 1 && rng.gen::<f32>() < mutation.deletion {
+            self.0.remove(rng.gen_range(0..self.0.len()));
+            mutations.deletions += 1;
+        }
+
+        if mutation.traits > 0. {
+            mutations.traits += self.1.mutate(mutation.traits, rng);
+        }
+
+        if mutation.activations > 0. {
+            mutations.activations += self.2.mutate_activations(mutation.activations, rng);
+        }
 
-        if let Some(mutation) = self.0.choose_mut(&mut rand::thread_rng()) {
-            *mutation = mutation.mutate(hidden_neurons);
+        if mutation.biases > 0. {
+            mutations.biases += self.2.mutate_biases(mutation.biases, rng);
         }
+
+        mutations
+    }
+
+    fn build(&self, neural: Neural) -> Brain {
+        Brain::new(
+            self.0.iter().copied().map(Gene::build),
+            |index| Soma {
+                activation: if neural.evolved {
+                    self.2.activation(index)
+                } else {
+                    neural.activation
+                },
+                bias: self.2.hidden_bias(index),
+            },
+            |output| Soma {
+                activation: neural.activation,
+                bias: self.2.output_bias(output as u8),
+            },
+        )
+    }
+
+    pub fn save(&self, writer: &mut impl Writer) -> std::io::Result<()> {
+        writer.write_usize(self.0.len())?;
+        for gene in &self.0 {
+            writer.write_u32(gene.0)?;
+        }
+        self.1.save(writer)?;
+        self.2.save(writer)
     }
 
-    fn build(&self) -> Brain {
-        Brain::new(self.0.iter().copied().map(Gene::build))
+    pub fn load(reader: &mut impl Reader, hidden_neurons: u8) -> anyhow::Result<Self> {
+        let count = reader.read_usize()?;
+        let genes = (0..count)
+            .map(|_| Ok(Gene::new(reader.read_u32()?, hidden_neurons)))
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(genes, Traits::load(reader)?, Neurons::load(reader)?))
+    }
+
+    /// Parses the whitespace separated hex gene words written by the [`Display`](std::fmt::Display)
+    /// implementation, followed by `|` and the traits, then `|` and the neurons
+    pub fn parse(text: &str, hidden_neurons: u8) -> anyhow::Result<Self> {
+        let mut parts = text.split('|');
+        let (Some(genes), Some(traits), Some(neurons), None) =
+            (parts.next(), parts.next(), parts.next(), parts.next())
+        else {
+            anyhow::bail!("Genome `{text}` must be genes, traits and neurons separated by `|`");
+        };
+
+        let genes = genes
+            .split_whitespace()
+            .map(|word| {
+                u32::from_str_radix(word, 16)
+                    .map(|gene| Gene::new(gene, hidden_neurons))
+                    .map_err(|e| anyhow::anyhow!("Invalid gene `{word}`: {e}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+
+        Ok(Self(
+            genes,
+            Traits::parse(traits.trim())?,
+            Neurons::parse(neurons.trim())?,
+        ))
+    }
+}
+
+impl std::fmt::Display for Genome {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut genes = self.0.iter();
+        if let Some(gene) = genes.next() {
+            write!(f, "{:08x}", gene.0)?;
+        }
+        for gene in genes {
+            write!(f, " {:08x}", gene.0)?;
+        }
+        write!(f, " | {} | {}", self.1, self.2)
     }
 }
 
-// TODO: Control physical traits of the being as well
 #[derive(Copy, Clone)]
 struct Gene(u32);
 
 impl Gene {
     const TWENTY_BITS: u32 = (1_u32 << 20) - 1;
 
+    // ALLOWED: Mantissa is 23 bits, this is only 18
+    #[allow(clippy::cast_precision_loss)]
+    // 20 bits set to `1`
+    // Divided by eight
+    const REFERENCE: f32 = ((1_u32 << 17) - 1) as f32;
+
     fn new(mut gene: u32, hidden_neurons: u8) -> Self {
         let (mut conn_type, mut input, mut output) = Self::dissect(gene);
         if hidden_neurons == 0 {
@@ -94,16 +314,16 @@ impl Gene {
         }
 
         if conn_type & 0b10 == 0 {
-            if input > Input::Random as u8 {
-                input %= Input::Random as u8 + 1;
+            if input >= Input::COUNT {
+                input %= Input::COUNT;
             }
         } else if input >= hidden_neurons {
             input %= hidden_neurons;
         }
 
         if conn_type == 0 || conn_type == 3 {
-            if output > Output::Noop as u8 {
-                output %= Output::Noop as u8 + 1;
+            if output >= Output::COUNT {
+                output %= Output::COUNT;
             }
         } else if output >= hidden_neurons {
             output %= hidden_neurons;
@@ -117,11 +337,62 @@ impl Gene {
         Self(gene)
     }
 
-    fn mutate(self, hidden_neurons: u8) -> Self {
-        let bit = 1 << (rand::random::<u8>() % 31_u8);
+    /// Applies a point mutation of a kind picked by `mutation`
+    fn point(
+        self,
+        mutation: &Mutation,
+        hidden_neurons: u8,
+        rng: &mut impl rand::Rng,
+    ) -> (Self, Kind) {
+        let kind = mutation.kind(rng);
+        let gene = match kind {
+            Kind::Flip => self.mutate(hidden_neurons, rng),
+            Kind::Nudge => self.nudge(mutation.sigma, rng),
+            Kind::Rewire => self.rewire(hidden_neurons, rng),
+        };
+        (gene, kind)
+    }
+
+    fn mutate(self, hidden_neurons: u8, rng: &mut impl rand::Rng) -> Self {
+        let bit = 1 << (rng.gen::<u8>() % 31_u8);
         Self::new(self.0 ^ bit, hidden_neurons)
     }
 
+    fn nudge(self, sigma: f32, rng: &mut impl rand::Rng) -> Self {
+        use rand_distr::Distribution;
+
+        let noise = rand_distr::Normal::new(0., sigma).map_or(0., |normal| normal.sample(rng));
+        let weight = ((self.weight() + noise + 4.) * Self::REFERENCE).round();
+
+        // ALLOWED: Clamped to the 20 bit range, which fits the mantissa, before casting
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_precision_loss,
+            clippy::cast_sign_loss
+        )]
+        let weight = weight.clamp(0., Self::TWENTY_BITS as f32) as u32;
+
+        Self((self.0 & !Self::TWENTY_BITS) | weight)
+    }
+
+    fn rewire(self, hidden_neurons: u8, rng: &mut impl rand::Rng) -> Self {
+        let wiring = rng.gen::<u32>() & !Self::TWENTY_BITS;
+        Self::new(wiring | (self.0 & Self::TWENTY_BITS), hidden_neurons)
+    }
+
+    /// Everything but the synapse weight
+    fn wiring(self) -> u32 {
+        self.0 & !Self::TWENTY_BITS
+    }
+
+    fn weight(self) -> f32 {
+        // ALLOWED: Mantissa is 23 bits, this is only 20
+        #[allow(clippy::cast_precision_loss)]
+        // REFERENCE is divided by 8 so that we ultimately multiply `weight` by 8
+        let weight = (self.0 & Self::TWENTY_BITS) as f32 / Self::REFERENCE - 4.;
+        weight
+    }
+
     #[inline]
     fn dissect(mut gene: u32) -> (u8, u8, u8) {
         gene >>= 20;
@@ -138,16 +409,7 @@ impl Gene {
     }
 
     fn build(self) -> Axon {
-        // ALLOWED: Mantissa is 23 bits, this is only 18
-        #[allow(clippy::cast_precision_loss)]
-        // 20 bits set to `1`
-        // Divided by eight
-        const REFERENCE: f32 = ((1_u32 << 17) - 1) as f32;
-
-        // ALLOWED: Mantissa is 23 bits, this is only 20
-        #[allow(clippy::cast_precision_loss)]
-        // REFERENCE is divided by 8 so that we ultimately multiply `weight` by 8
-        let synapse = (self.0 & Self::TWENTY_BITS) as f32 / REFERENCE - 4.;
+        let synapse = self.weight();
 
         let (conn_type, input, output) = Self::dissect(self.0);
 
@@ -165,118 +427,191 @@ impl Gene {
     }
 }
 
+/// What a boop senses of itself and the world around it
+///
+/// Angles are sensed as a sine and cosine pair, which unlike the angle itself has no jump when
+/// going full circle. Variants are only ever appended, so that saved genomes keep their wiring
 // ALLOWED: Input::from() may build this
 #[allow(dead_code)]
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 enum Input {
-    // TODO: Make `direction` a single input
-    Direction,
-    // DirectionVertical,
-    // DirectionHorizontal,
-    FoodDirection,
+    /// Sine of the own direction
+    DirectionSine,
+    /// Sine of the direction of the nearest food, relative to the own direction
+    FoodSine,
     FoodDistance,
     Unit,
     Random,
+    /// Closeness of the nearest wall or border straight ahead
+    WallDistance,
+    /// Energy left, from empty to a full tank
+    Energy,
+    /// Closeness of the nearest boop in sight
+    NeighbourDistance,
+    /// Sine of the direction of the nearest boop in sight, relative to the own direction
+    NeighbourSine,
+    /// Number of boops in sight, relative to a full crowd
+    Density,
+    /// Share of signature bits the nearest boop in sight has in common
+    Kinship,
+    /// Cosine of the own direction
+    DirectionCosine,
+    /// Cosine of the direction of the nearest food, relative to the own direction
+    FoodCosine,
+    /// Cosine of the direction of the nearest boop in sight, relative to the own direction
+    NeighbourCosine,
 }
 
+// Inputs are encoded on 5 bits of a gene
+const _: () = assert!(Input::COUNT <= 1 << 5);
+
 impl Input {
+    /// Number of variants, which a `Gene` wraps its input index around
+    const COUNT: u8 = Self::NeighbourCosine as u8 + 1;
+
     fn from(index: u8) -> Self {
         // SAFETY: This private method is only called by a `Gene`
         unsafe { std::mem::transmute::<u8, Self>(index) }
     }
 
-    fn sense(self, simulation: &Simulation, index: Index) -> Stimulus {
+    /// Sine of `angle`, mapped to the stimulus range
+    fn sine(angle: Direction) -> Stimulus {
+        Stimulus::cap(f32::midpoint(angle.as_rad().sin(), 1.))
+    }
+
+    /// Cosine of `angle`, mapped to the stimulus range
+    fn cosine(angle: Direction) -> Stimulus {
+        Stimulus::cap(f32::midpoint(angle.as_rad().cos(), 1.))
+    }
+
+    /// What the boop at `index` perceives
+    ///
+    /// Several senses look at the same neighbours, who are only surveyed once and kept in
+    /// `neighbours` for the rest of the day
+    fn sense(
+        self,
+        body: &Body,
+        world: &World,
+        index: Index,
+        noise: f32,
+        neighbours: &std::cell::OnceCell<Neighbours>,
+    ) -> Stimulus {
+        let neighbours =
+            || *neighbours.get_or_init(|| world.neighbours(index, SIGHT * body.sensing()));
+
         match self {
-            Self::Direction => {
-                Stimulus::cap(simulation.boop(index).direction().as_rad() / std::f32::consts::TAU)
+            Self::DirectionSine => Self::sine(body.direction()),
+            Self::DirectionCosine => Self::cosine(body.direction()),
+            Self::FoodSine | Self::FoodCosine => {
+                world
+                    .nearest_food(index)
+                    .map_or(Stimulus::from(false), |(food, _)| {
+                        let angle = food.dir_from(world.boop(index)) - body.direction();
+                        if self == Self::FoodSine {
+                            Self::sine(angle)
+                        } else {
+                            Self::cosine(angle)
+                        }
+                    })
             }
-            // Self::DirectionVertical => {
-            //     Stimulus::cap(simulation.boop(index).direction().as_rad().sin() + 1. / 2.)
-            // }
-            // Self::DirectionHorizontal => {
-            //     Stimulus::cap(simulation.boop(index).direction().as_rad().cos() + 1. / 2.)
-            // }
-            Self::FoodDirection => {
-                let boop = simulation.boop(index);
-                let coord = simulation.world.boop(index);
-                let mut food = simulation
-                    .fodder()
-                    .map(|f| (f, f.distance(coord)))
-                    // .filter_map(|f| {
-                    //     let d = f.distance(coord);
-                    //     if d <= 10. {
-                    //         Some((f, d))
-                    //     } else {
-                    //         None
-                    //     }
-                    // })
-                    .collect::<Vec<_>>();
-                food.sort_unstable_by(|(_, d1), (_, d2)| {
-                    d1.partial_cmp(d2).unwrap_or(std::cmp::Ordering::Equal)
-                });
-                if let Some((f, _)) = food.first() {
-                    Stimulus::cap(
-                        (f.dir_from(coord) - boop.direction()).as_rad() / std::f32::consts::TAU,
-                    )
-                } else {
-                    Stimulus::from(false)
-                }
+            Self::FoodDistance => world
+                .food_distance(index)
+                .map_or(Stimulus::from(false), |d| {
+                    Stimulus::cap(d / f32::from(world.size()))
+                }),
+            Self::Unit => Stimulus::from(true),
+            Self::Random => Stimulus::cap(noise),
+            Self::WallDistance => {
+                let sight = SIGHT * body.sensing();
+                world
+                    .terrain()
+                    .wall_ahead(world.boop(index), body.direction(), sight)
+                    .map_or(Stimulus::from(false), |d| Stimulus::cap(1. - d / sight))
             }
-            Self::FoodDistance => {
-                let coord = simulation.world.boop(index);
-                let mut food = simulation
-                    .fodder()
-                    .map(|f| f.distance(coord))
-                    .collect::<Vec<_>>();
-                food.sort_unstable_by(|d1, d2| {
-                    d1.partial_cmp(d2).unwrap_or(std::cmp::Ordering::Equal)
-                });
-                food.first().map_or(Stimulus::from(false), |d| {
-                    Stimulus::cap(*d / f32::from(simulation.size()))
-                })
+            Self::Energy => Stimulus::cap(world.energy(index)),
+            Self::NeighbourDistance => neighbours()
+                .nearest
+                .map_or(Stimulus::from(false), |(_, d)| {
+                    Stimulus::cap(1. - d / (SIGHT * body.sensing()))
+                }),
+            Self::NeighbourSine | Self::NeighbourCosine => {
+                neighbours()
+                    .nearest
+                    .map_or(Stimulus::from(false), |(other, _)| {
+                        let angle =
+                            world.boop(other).dir_from(world.boop(index)) - body.direction();
+                        if self == Self::NeighbourSine {
+                            Self::sine(angle)
+                        } else {
+                            Self::cosine(angle)
+                        }
+                    })
             }
-            Self::Unit => Stimulus::from(true),
-            Self::Random => Stimulus::cap(rand::random::<f32>()),
+            Self::Density => {
+                // ALLOWED: Only a handful of boops fit in sight before the stimulus saturates
+                #[allow(clippy::cast_precision_loss)]
+                let count = neighbours().count as f32;
+                Stimulus::cap(count / CROWD)
+            }
+            Self::Kinship => neighbours()
+                .nearest
+                .map_or(Stimulus::from(false), |(other, _)| {
+                    let differing = (world.signature(index) ^ world.signature(other)).count_ones();
+                    // ALLOWED: At most 32 bits differ
+                    #[allow(clippy::cast_precision_loss)]
+                    Stimulus::cap(1. - differing as f32 / 32.)
+                }),
         }
     }
 }
 
 // ALLOWED: Output::from() may build this
 #[allow(dead_code)]
-#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Ord, PartialOrd, Eq, PartialEq, Hash, Debug)]
 enum Output {
     TurnLeft,
     TurnRight,
     Advance,
     Noop,
+    /// Turns clockwise for a positive response, and counter-clockwise for a negative one
+    Turn,
 }
 
+// Outputs are encoded on 5 bits of a gene
+const _: () = assert!(Output::COUNT <= 1 << 5);
+
 impl Output {
+    /// Number of variants, which a `Gene` wraps its output index around
+    const COUNT: u8 = Self::Turn as u8 + 1;
+
     fn from(index: u8) -> Self {
         // SAFETY: This private method is only called by a `Gene`
         unsafe { std::mem::transmute::<u8, Self>(index) }
     }
 
-    fn spike(self, stimulus: Stimulus) -> Option<(Self, Stimulus)> {
+    fn spike(self, response: Response) -> Option<(Self, Response)> {
         match self {
-            Self::TurnLeft | Self::TurnRight | Self::Advance => Some((self, stimulus)),
+            Self::TurnLeft | Self::TurnRight | Self::Advance | Self::Turn => Some((self, response)),
             Self::Noop => None,
         }
     }
 
-    fn act(self, simulation: &mut Simulation, index: Index, stimulus: Stimulus) {
+    /// Only [`Self::Turn`] uses the signed range, the others ignore a negative response
+    fn act(self, intent: &mut Intent, response: Response) {
+        let stimulus = Stimulus::cap(response.as_f32()).as_f32();
         match self {
             Self::TurnLeft => {
-                simulation.boop_mut(index).turn_left(stimulus.as_f32());
+                intent.turn -= stimulus;
+                intent.steering += stimulus;
             }
             Self::TurnRight => {
-                simulation.boop_mut(index).turn_right(stimulus.as_f32());
+                intent.turn += stimulus;
+                intent.steering += stimulus;
             }
-            Self::Advance => {
-                let direction = simulation.boop(index).direction();
-                simulation
-                    .world
-                    .advance(index, stimulus.as_f32(), direction);
+            Self::Advance => intent.advance += stimulus,
+            Self::Turn => {
+                intent.turn += response.as_f32();
+                intent.steering += response.as_f32().abs();
             }
             Self::Noop => {}
         }
@@ -285,7 +620,8 @@ impl Output {
 
 #[cfg(test)]
 mod test {
-    use super::{Gene, Input, Output};
+    use super::{Gene, Genome, Input, Output};
+    use crate::sim::mutation::{Kinds, Mutation};
 
     #[test]
     fn gene_is_safe() {
@@ -300,11 +636,11 @@ mod test {
 
                 match conn_type {
                     0 => {
-                        assert!(input <= Input::Random as u8, "{}", input,);
-                        assert!(output <= Output::Noop as u8, "{}", output,);
+                        assert!(input < Input::COUNT, "{}", input,);
+                        assert!(output < Output::COUNT, "{}", output,);
                     }
                     1 => {
-                        assert!(input <= Input::Random as u8, "{}", input,);
+                        assert!(input < Input::COUNT, "{}", input,);
                         assert!(output < i);
                     }
                     2 => {
@@ -313,7 +649,7 @@ mod test {
                     }
                     3 => {
                         assert!(input < i);
-                        assert!(output <= Output::Noop as u8, "{}", output,);
+                        assert!(output < Output::COUNT, "{}", output,);
                     }
                     _ => unreachable!(),
                 }
@@ -323,13 +659,135 @@ mod test {
 
     #[test]
     fn mutation() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
         let gene = Gene::new(rand::random(), 8);
         let mut reference: u32;
 
         for _ in 0..10 {
             reference = gene.0;
-            gene.mutate(8);
+            gene.mutate(8, &mut rng);
             assert_eq!((reference.max(gene.0) - reference.min(gene.0)) % 2, 0);
         }
     }
+
+    #[test]
+    fn nudge_and_rewire() {
+        use rand::{Rng, SeedableRng};
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        for _ in 0..10 {
+            let gene = Gene::new(rng.gen(), 8);
+
+            let nudged = gene.nudge(0.5, &mut rng);
+            assert_eq!(gene.0 & !Gene::TWENTY_BITS, nudged.0 & !Gene::TWENTY_BITS);
+            assert!((-4.0..=4.1).contains(&nudged.weight()));
+
+            let rewired = gene.rewire(8, &mut rng);
+            assert_eq!(gene.0 & Gene::TWENTY_BITS, rewired.0 & Gene::TWENTY_BITS);
+        }
+    }
+
+    #[test]
+    fn structural_mutation() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+        let mut genome = Genome::random(8, 4, &mut rng);
+
+        let grow = Mutation {
+            rate: 0.,
+            duplication: 1.,
+            ..Mutation::default()
+        };
+        let mutations = genome.mutate(&grow, 4, &mut rng);
+        assert_eq!(mutations.duplications, 1);
+        assert_eq!(genome.0.len(), 9);
+
+        let shrink = Mutation {
+            rate: 0.,
+            deletion: 1.,
+            ..Mutation::default()
+        };
+        let mutations = genome.mutate(&shrink, 4, &mut rng);
+        assert_eq!(mutations.deletions, 1);
+        assert_eq!(genome.0.len(), 8);
+
+        let limited = Mutation {
+            rate: 1.,
+            gene_rate: 1.,
+            limit: Some(3),
+            kinds: Kinds {
+                flip: 0.,
+                nudge: 1.,
+                rewire: 1.,
+            },
+            ..Mutation::default()
+        };
+        let mutations = genome.mutate(&limited, 4, &mut rng);
+        assert_eq!(mutations.total(), 3);
+        assert_eq!(mutations.flips, 0);
+
+        // Without a per gene rate, an offspring gets at most one point mutation
+        let once = Mutation {
+            rate: 1.,
+            ..Mutation::default()
+        };
+        assert_eq!(genome.mutate(&once, 4, &mut rng).total(), 1);
+    }
+
+    #[test]
+    fn opposing_turns() {
+        use super::{Intent, Response};
+
+        // Turning both ways goes nowhere, but still takes the effort of both turns
+        let mut intent = Intent::default();
+        Output::TurnLeft.act(&mut intent, Response::cap(0.5));
+        Output::TurnRight.act(&mut intent, Response::cap(0.5));
+        Output::Turn.act(&mut intent, Response::cap(-0.25));
+        assert!((intent.turn + 0.25).abs() < f32::EPSILON);
+        assert!((intent.steering - 1.25).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn neighbour_senses() {
+        use super::super::body::Body;
+        use super::super::traits::Traits;
+        use crate::sim::terrain::Terrain;
+        use crate::sim::world::{Coordinate, Direction, World};
+        use crate::sim::Index;
+
+        let mut world = World::with(
+            Terrain::open(32),
+            vec![
+                Coordinate::new(4., 4.),
+                Coordinate::new(4., 6.),
+                Coordinate::new(24., 24.),
+            ],
+            vec![],
+        );
+        world.embody(Index(0), 1., 0xffff_0000);
+        world.embody(Index(1), 1., 0xffff_ffff);
+        world.embody(Index(2), 1., 0xffff_0000);
+
+        let east = Body::with(Direction::from_rad(0.), Traits::neutral(0));
+        let sense = |input: Input, index| {
+            let neighbours = std::cell::OnceCell::new();
+            input
+                .sense(&east, &world, Index(index), 0., &neighbours)
+                .as_f32()
+        };
+
+        assert!((sense(Input::NeighbourDistance, 0) - 0.75).abs() < 1e-6);
+        assert!((sense(Input::NeighbourSine, 0) - 1.).abs() < 1e-6);
+        assert!((sense(Input::NeighbourCosine, 0) - 0.5).abs() < 1e-6);
+        assert!((sense(Input::DirectionSine, 0) - 0.5).abs() < 1e-6);
+        assert!((sense(Input::DirectionCosine, 0) - 1.).abs() < 1e-6);
+        assert!((sense(Input::Density, 0) - 1. / 8.).abs() < 1e-6);
+        assert!((sense(Input::Kinship, 0) - 0.5).abs() < 1e-6);
+
+        // Nobody in sight
+        assert!(sense(Input::NeighbourDistance, 2).abs() < f32::EPSILON);
+        assert!(sense(Input::Density, 2).abs() < f32::EPSILON);
+    }
 }
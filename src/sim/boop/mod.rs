@@ -1,42 +1,82 @@
 mod body;
 mod mind;
+mod neurons;
+mod traits;
 
+use super::crossover::Crossover;
+use super::mutation::{Mutation, Mutations};
+use super::snapshot::{Reader, Writer};
+use super::Direction;
 use body::Body;
-use mind::Mind;
 
+pub use mind::{Genome, Minds, Neural, MAX_HIDDEN_NEURONS};
+
+/// Body of a boop and the genome it was expressed from, its brain lives in [`Minds`]
 pub struct Boop {
-    mind: Mind,
+    genome: Genome,
     body: Body,
 }
 
+/// What a boop decided to do during a day, applied once every boop has decided
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+pub struct Intent {
+    /// Clockwise rotation, applied before moving
+    pub turn: f32,
+    /// Rotation asked for in either direction, even if opposite turns cancel out
+    pub steering: f32,
+    pub advance: f32,
+}
+
 impl Boop {
-    pub fn new(synapses: u16, hidden_neurons: u8) -> Self {
+    pub fn new(synapses: u16, hidden_neurons: u8, rng: &mut impl rand::Rng) -> Self {
+        Self::from_genome(Genome::random(synapses, hidden_neurons, rng), rng)
+    }
+
+    pub fn from_genome(genome: Genome, rng: &mut impl rand::Rng) -> Self {
         Self {
-            mind: Mind::random(synapses, hidden_neurons),
-            body: Body::new(),
+            body: Body::new(genome.traits(), rng),
+            genome,
         }
     }
 
     #[inline]
-    pub fn signature(&self) -> u32 {
-        self.mind.genome().signature()
+    pub fn genome(&self) -> &Genome {
+        &self.genome
     }
 
     #[inline]
-    pub fn mind_mut(&mut self) -> &mut Mind {
-        &mut self.mind
+    pub fn signature(&self) -> u32 {
+        self.genome.signature()
     }
 
     #[inline]
-    pub fn mate(&self, mate: &Self, mutability: f32, hidden_neurons: u8) -> Self {
-        let mut spawn = self.mind.genome().combine(mate.mind.genome());
-        if rand::random::<f32>() < mutability {
-            spawn.mutate(hidden_neurons);
-        }
-        Self {
-            mind: Mind::from(spawn),
-            body: Body::new(),
-        }
+    pub fn mate(
+        &self,
+        mate: &Self,
+        crossover: &Crossover,
+        mutation: &Mutation,
+        hidden_neurons: u8,
+        rng: &mut impl rand::Rng,
+    ) -> (Self, Mutations) {
+        let mut spawn = self.genome.combine(&mate.genome, crossover, rng);
+        let mutations = spawn.mutate(mutation, hidden_neurons, rng);
+        let spawn = Self::from_genome(spawn, rng);
+        (spawn, mutations)
+    }
+
+    pub fn save(&self, writer: &mut impl Writer) -> std::io::Result<()> {
+        self.body.save(writer)?;
+        self.genome.save(writer)
+    }
+
+    pub fn load(reader: &mut impl Reader, hidden_neurons: u8) -> anyhow::Result<Self> {
+        // The body is only written as its direction, and expressed from the genome
+        let direction = Direction::load(reader)?;
+        let genome = Genome::load(reader, hidden_neurons)?;
+        Ok(Self {
+            body: Body::with(direction, genome.traits()),
+            genome,
+        })
     }
 }
 
@@ -0,0 +1,219 @@
+use super::super::snapshot::{Reader, Writer};
+use super::mind::{MAX_HIDDEN_NEURONS, OUTPUTS};
+use crate::neural::Activation;
+
+/// Value of a bias gene that never evolved, expressed as a bias of 0
+const NEUTRAL: u8 = 128;
+
+/// Standard deviation of a bias mutation, in gene units
+const SIGMA: f32 = 16.;
+
+/// Genes of the neurons, a byte each
+///
+/// The first bytes pick the activation function of every hidden neuron. They are followed by the
+/// bias of every hidden neuron and then of every output, from -4 to 4 like synapse weights.
+/// Genes are saved along with how many there are, so that genomes saved before an output was
+/// added read as neutral for it
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Neurons([u8; Self::LEN]);
+
+impl Neurons {
+    const HIDDEN: usize = MAX_HIDDEN_NEURONS as usize;
+    const LEN: usize = Self::HIDDEN * 2 + OUTPUTS as usize;
+
+    const ACTIVATIONS: usize = 0;
+    const BIASES: usize = Self::HIDDEN;
+    const OUTPUT_BIASES: usize = Self::BIASES + Self::HIDDEN;
+
+    /// Neurons of a genome that never evolved any, activated with `tanh` and without bias
+    pub fn neutral() -> Self {
+        let mut neurons = [NEUTRAL; Self::LEN];
+        neurons[Self::ACTIVATIONS..Self::BIASES].fill(0);
+        Self(neurons)
+    }
+
+    /// Activation function of the hidden neuron at `index`
+    pub fn activation(self, index: u8) -> Activation {
+        let gene = self.0[Self::ACTIVATIONS + usize::from(index) % Self::HIDDEN];
+        Activation::ALL[usize::from(gene) % Activation::ALL.len()]
+    }
+
+    /// Bias of the hidden neuron at `index`
+    pub fn hidden_bias(self, index: u8) -> f32 {
+        Self::bias(self.0[Self::BIASES + usize::from(index) % Self::HIDDEN])
+    }
+
+    /// Bias of the output neuron at `index`
+    pub fn output_bias(self, index: u8) -> f32 {
+        Self::bias(self.0[Self::OUTPUT_BIASES + usize::from(index % OUTPUTS)])
+    }
+
+    fn bias(gene: u8) -> f32 {
+        (f32::from(gene) - f32::from(NEUTRAL)) / 32.
+    }
+
+    /// Every neuron gene taken from either parent with even chance
+    pub fn combine(self, other: Self, rng: &mut impl rand::Rng) -> Self {
+        let mut neurons = self;
+        for (mine, theirs) in neurons.0.iter_mut().zip(other.0) {
+            if rng.gen::<bool>() {
+                *mine = theirs;
+            }
+        }
+        neurons
+    }
+
+    /// Switches every hidden neuron to a random activation function with chance `rate`,
+    /// returning how many were
+    pub fn mutate_activations(&mut self, rate: f32, rng: &mut impl rand::Rng) -> usize {
+        let mut mutated = 0;
+        for gene in &mut self.0[Self::ACTIVATIONS..Self::BIASES] {
+            if rng.gen::<f32>() < rate {
+                *gene = rng.gen();
+                mutated += 1;
+            }
+        }
+        mutated
+    }
+
+    /// Nudges every bias with chance `rate`, returning how many were
+    pub fn mutate_biases(&mut self, rate: f32, rng: &mut impl rand::Rng) -> usize {
+        use rand_distr::Distribution;
+
+        let mut mutated = 0;
+        for gene in &mut self.0[Self::BIASES..] {
+            if rng.gen::<f32>() < rate {
+                let noise = rand_distr::Normal::new(0., SIGMA).map_or(0., |n| n.sample(rng));
+
+                // ALLOWED: Clamped to the byte range before casting
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let nudged = (f32::from(*gene) + noise).round().clamp(0., 255.) as u8;
+                *gene = nudged;
+                mutated += 1;
+            }
+        }
+        mutated
+    }
+
+    pub fn save(self, writer: &mut impl Writer) -> std::io::Result<()> {
+        const _: () = assert!(Neurons::LEN <= u8::MAX as usize);
+
+        // ALLOWED: Checked above
+        #[allow(clippy::cast_possible_truncation)]
+        writer.write_u8(Self::LEN as u8)?;
+        self.0.iter().try_for_each(|gene| writer.write_u8(*gene))
+    }
+
+    /// Reads neurons written by [`Self::save`]
+    pub fn load(reader: &mut impl Reader) -> anyhow::Result<Self> {
+        let len = usize::from(reader.read_u8()?);
+        if !(Self::OUTPUT_BIASES..=Self::LEN).contains(&len) {
+            anyhow::bail!(
+                "Snapshot has {len} neuron genes, from {} to {} are supported",
+                Self::OUTPUT_BIASES,
+                Self::LEN
+            );
+        }
+
+        let mut neurons = Self::neutral();
+        for gene in &mut neurons.0[..len] {
+            *gene = reader.read_u8()?;
+        }
+        Ok(neurons)
+    }
+
+    /// Parses the hex word written by the [`Display`](std::fmt::Display) implementation
+    ///
+    /// Shorter words were written when there were fewer outputs, the biases they lack are neutral
+    pub fn parse(word: &str) -> anyhow::Result<Self> {
+        if !word.len().is_multiple_of(2)
+            || !(Self::OUTPUT_BIASES * 2..=Self::LEN * 2).contains(&word.len())
+        {
+            anyhow::bail!(
+                "Neurons `{word}` must be an even number of hex digits, from {} to {}",
+                Self::OUTPUT_BIASES * 2,
+                Self::LEN * 2
+            );
+        }
+
+        let mut neurons = Self::neutral();
+        for (i, gene) in neurons.0[..word.len() / 2].iter_mut().enumerate() {
+            *gene = word
+                .get(i * 2..i * 2 + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid neurons `{word}`"))?;
+        }
+        Ok(neurons)
+    }
+}
+
+impl std::fmt::Display for Neurons {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|gene| write!(f, "{gene:02x}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Neurons;
+    use crate::neural::Activation;
+
+    #[test]
+    fn activations() {
+        assert_eq!(Neurons::neutral().activation(3), Activation::Tanh);
+
+        let word = format!("0001020304050607{}", "80".repeat(Neurons::LEN - 8));
+        let neurons = Neurons::parse(&word).unwrap();
+        assert_eq!(neurons.activation(2), Activation::Relu);
+        assert_eq!(neurons.activation(7), Activation::Sigmoid);
+        assert!(neurons.hidden_bias(2).abs() < f32::EPSILON);
+        assert_eq!(Neurons::parse(&neurons.to_string()).unwrap(), neurons);
+        assert!(Neurons::parse("0001020304050607").is_err());
+        assert!(Neurons::parse(&word[1..]).is_err());
+        assert!(Neurons::parse(&"00".repeat(Neurons::LEN + 1)).is_err());
+    }
+
+    #[test]
+    fn fewer_genes() {
+        use crate::sim::snapshot::Writer;
+
+        // Saved with one output less than there are now
+        let mut saved = vec![];
+        let len = u8::try_from(Neurons::LEN - 1).unwrap();
+        saved.write_u8(len).unwrap();
+        saved.extend(std::iter::repeat_n(255, Neurons::LEN - 1));
+
+        let neurons = Neurons::load(&mut saved.as_slice()).unwrap();
+        assert!(neurons.output_bias(0) > 3.9);
+        assert!(neurons.output_bias(super::OUTPUTS - 1).abs() < f32::EPSILON);
+
+        let parsed = Neurons::parse(&"ff".repeat(Neurons::LEN - 1)).unwrap();
+        assert_eq!(parsed, neurons);
+
+        let mut written = vec![];
+        neurons.save(&mut written).unwrap();
+        assert_eq!(Neurons::load(&mut written.as_slice()).unwrap(), neurons);
+    }
+
+    #[test]
+    fn biases() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let neutral = Neurons::neutral();
+        assert!(neutral.output_bias(0).abs() < f32::EPSILON);
+
+        let mut neurons = neutral;
+        neurons.0[Neurons::BIASES] = 0;
+        neurons.0[Neurons::LEN - 1] = 255;
+        assert!((neurons.hidden_bias(0) + 4.).abs() < f32::EPSILON);
+        assert!(neurons.output_bias(super::OUTPUTS - 1) > 3.9);
+
+        let mut mutant = neutral;
+        assert_eq!(
+            mutant.mutate_biases(1., &mut rng),
+            Neurons::LEN - Neurons::HIDDEN
+        );
+        assert_eq!(mutant.0[..Neurons::BIASES], neutral.0[..Neurons::BIASES]);
+    }
+}
@@ -0,0 +1,161 @@
+use super::super::snapshot::{Reader, Writer};
+
+/// Value of a trait in a genome that never evolved it, expressed as a factor of 1
+const NEUTRAL: u8 = 128;
+
+/// Standard deviation of a trait mutation, in trait units
+const SIGMA: f32 = 16.;
+
+/// Non-neural genes, expressed into the body at birth
+///
+/// Every trait is a byte. Speed, turn rate, size and sensing are expressed as a factor from half
+/// to double, while the last three bytes are the colour
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Traits([u8; Self::LEN]);
+
+impl Traits {
+    const LEN: usize = 7;
+
+    const SPEED: usize = 0;
+    const TURN_RATE: usize = 1;
+    const SIZE: usize = 2;
+    const SENSING: usize = 3;
+    const COLOUR: usize = 4;
+
+    /// Traits of a genome that never evolved any, coloured after its `signature`
+    pub fn neutral(signature: u32) -> Self {
+        let [_, r, g, b] = signature.to_be_bytes();
+        Self([NEUTRAL, NEUTRAL, NEUTRAL, NEUTRAL, r, g, b])
+    }
+
+    fn factor(self, index: usize) -> f32 {
+        2_f32.powf((f32::from(self.0[index]) - f32::from(NEUTRAL)) / f32::from(NEUTRAL))
+    }
+
+    /// Farthest a boop moves in a day
+    pub fn speed(self) -> f32 {
+        self.factor(Self::SPEED)
+    }
+
+    /// Most a boop turns in a day, in radians
+    pub fn turn_rate(self) -> f32 {
+        self.factor(Self::TURN_RATE)
+    }
+
+    /// How far a boop reaches for food
+    pub fn size(self) -> f32 {
+        self.factor(Self::SIZE)
+    }
+
+    /// How far a boop senses its surroundings
+    pub fn sensing(self) -> f32 {
+        self.factor(Self::SENSING)
+    }
+
+    /// As `0xRRGGBB`
+    pub fn colour(self) -> u32 {
+        let [r, g, b] = [
+            self.0[Self::COLOUR],
+            self.0[Self::COLOUR + 1],
+            self.0[Self::COLOUR + 2],
+        ];
+        u32::from_be_bytes([0, r, g, b])
+    }
+
+    /// Every trait taken from either parent with even chance
+    pub fn combine(self, other: Self, rng: &mut impl rand::Rng) -> Self {
+        let mut traits = self;
+        for (mine, theirs) in traits.0.iter_mut().zip(other.0) {
+            if rng.gen::<bool>() {
+                *mine = theirs;
+            }
+        }
+        traits
+    }
+
+    /// Nudges every trait with chance `rate`, returning how many were
+    pub fn mutate(&mut self, rate: f32, rng: &mut impl rand::Rng) -> usize {
+        use rand_distr::Distribution;
+
+        let mut mutated = 0;
+        for value in &mut self.0 {
+            if rng.gen::<f32>() < rate {
+                let noise = rand_distr::Normal::new(0., SIGMA).map_or(0., |n| n.sample(rng));
+
+                // ALLOWED: Clamped to the byte range before casting
+                #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+                let nudged = (f32::from(*value) + noise).round().clamp(0., 255.) as u8;
+                *value = nudged;
+                mutated += 1;
+            }
+        }
+        mutated
+    }
+
+    pub fn save(self, writer: &mut impl Writer) -> std::io::Result<()> {
+        self.0.iter().try_for_each(|value| writer.write_u8(*value))
+    }
+
+    pub fn load(reader: &mut impl Reader) -> anyhow::Result<Self> {
+        let mut traits = [0; Self::LEN];
+        for value in &mut traits {
+            *value = reader.read_u8()?;
+        }
+        Ok(Self(traits))
+    }
+
+    /// Parses the hex word written by the [`Display`](std::fmt::Display) implementation
+    pub fn parse(word: &str) -> anyhow::Result<Self> {
+        if word.len() != Self::LEN * 2 {
+            anyhow::bail!("Traits `{word}` must be {} hex digits", Self::LEN * 2);
+        }
+
+        let mut traits = [0; Self::LEN];
+        for (i, value) in traits.iter_mut().enumerate() {
+            *value = word
+                .get(i * 2..i * 2 + 2)
+                .and_then(|byte| u8::from_str_radix(byte, 16).ok())
+                .ok_or_else(|| anyhow::anyhow!("Invalid traits `{word}`"))?;
+        }
+        Ok(Self(traits))
+    }
+}
+
+impl std::fmt::Display for Traits {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.iter().try_for_each(|value| write!(f, "{value:02x}"))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Traits;
+
+    #[test]
+    fn neutral() {
+        let traits = Traits::neutral(0xdead_beef);
+        assert!((traits.speed() - 1.).abs() < f32::EPSILON);
+        assert!((traits.sensing() - 1.).abs() < f32::EPSILON);
+        assert_eq!(traits.colour(), 0x00ad_beef);
+
+        assert_eq!(Traits::parse(&traits.to_string()).unwrap(), traits);
+        assert!(Traits::parse("8080").is_err());
+        assert!(Traits::parse("808080zz000000").is_err());
+    }
+
+    #[test]
+    fn inherited() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let father = Traits([0; Traits::LEN]);
+        let mother = Traits([255; Traits::LEN]);
+        let child = father.combine(mother, &mut rng);
+        assert!(child.0.iter().all(|value| *value == 0 || *value == 255));
+
+        let mut mutant = child;
+        assert_eq!(mutant.mutate(0., &mut rng), 0);
+        assert_eq!(mutant, child);
+        assert_eq!(mutant.mutate(1., &mut rng), Traits::LEN);
+    }
+}
@@ -0,0 +1,322 @@
+use super::world::{Coordinate, Direction, World};
+use super::Index;
+
+/// Boops occupying space, so that they cannot move through each other
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Collision {
+    /// Every boop is a disc of this radius
+    pub radius: f32,
+    pub contact: Contact,
+    pub order: Order,
+}
+
+impl Default for Collision {
+    fn default() -> Self {
+        Self {
+            radius: 0.5,
+            contact: Contact::default(),
+            order: Order::default(),
+        }
+    }
+}
+
+/// Buffers of [`Collision::resolve`], kept to avoid allocating every day
+#[derive(Default)]
+pub struct Scratch {
+    order: Vec<usize>,
+    starts: Vec<Coordinate>,
+    targets: Vec<Coordinate>,
+    blocked: Vec<usize>,
+}
+
+/// What happens to a move that would overlap another boop
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Contact {
+    /// The boop stays where it is
+    #[default]
+    Block,
+    /// The other boop is shoved out of the way, unless something is behind it
+    Push,
+}
+
+/// Which moves are settled first
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Order {
+    /// One at a time, in a fresh random order every day
+    #[default]
+    Random,
+    /// All at once, every boop contending for the same spot is blocked
+    Simultaneous,
+}
+
+impl Collision {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !self.radius.is_finite() || self.radius <= 0. {
+            anyhow::bail!("Collision radius must be positive, got {}", self.radius);
+        }
+        if self.contact == Contact::Push && self.order == Order::Simultaneous {
+            anyhow::bail!("Pushing needs moves to be settled one at a time, in `random` order");
+        }
+        Ok(())
+    }
+
+    /// Moves every boop by `speed` towards its direction, as far as the others allow
+    pub fn resolve(
+        &self,
+        world: &mut World,
+        moves: &[(f32, Direction)],
+        scratch: &mut Scratch,
+        rng: &mut impl rand::Rng,
+    ) {
+        match self.order {
+            Order::Random => {
+                use rand::seq::SliceRandom;
+
+                scratch.order.clear();
+                scratch.order.extend(0..moves.len());
+                scratch.order.shuffle(rng);
+
+                for i in &scratch.order {
+                    let (speed, direction) = moves[*i];
+                    self.settle(world, Index(*i), speed, direction);
+                }
+            }
+            Order::Simultaneous => self.simultaneous(world, moves, scratch),
+        }
+    }
+
+    fn settle(&self, world: &mut World, index: Index, speed: f32, direction: Direction) {
+        let from = world.boop(index);
+        let target = world.destination(index, speed, direction);
+        if target == from {
+            return;
+        }
+
+        let Some(obstacle) = self.obstacle(world, index, from, target, None) else {
+            world.move_to(index, target);
+            return;
+        };
+
+        if self.contact == Contact::Push {
+            let shoved = self.shove(world, world.boop(obstacle), target, direction);
+            if world.terrain().is_passable(shoved)
+                && self
+                    .obstacle(world, obstacle, world.boop(obstacle), shoved, Some(index))
+                    .is_none()
+                && self
+                    .obstacle(world, index, from, target, Some(obstacle))
+                    .is_none()
+            {
+                world.move_to(obstacle, shoved);
+                world.move_to(index, target);
+            }
+        }
+    }
+
+    /// Another boop that moving from `from` to `target` would bump into
+    ///
+    /// Boops that already overlap may still move apart, only getting closer is a collision
+    fn obstacle(
+        &self,
+        world: &World,
+        index: Index,
+        from: Coordinate,
+        target: Coordinate,
+        ignore: Option<Index>,
+    ) -> Option<Index> {
+        let reach = self.radius * 2.;
+        world.boops_near(target, reach).find(|other| {
+            let coord = world.boop(*other);
+            *other != index
+                && Some(*other) != ignore
+                && coord.distance(target) < reach
+                && coord.distance(target) < coord.distance(from)
+        })
+    }
+
+    /// Where `occupant` ends up when shoved away from a boop arriving at `target`
+    fn shove(
+        &self,
+        world: &World,
+        occupant: Coordinate,
+        target: Coordinate,
+        direction: Direction,
+    ) -> Coordinate {
+        let reach = self.radius * 2.;
+        let distance = occupant.distance(target);
+        let (dx, dy) = if distance > f32::EPSILON {
+            let away = occupant - target;
+            (away.x() / distance, away.y() / distance)
+        } else {
+            (direction.as_rad().cos(), direction.as_rad().sin())
+        };
+
+        world.clamp(Coordinate::new(
+            target.x() + dx * reach,
+            target.y() + dy * reach,
+        ))
+    }
+
+    fn simultaneous(&self, world: &mut World, moves: &[(f32, Direction)], scratch: &mut Scratch) {
+        let reach = self.radius * 2.;
+        let Scratch {
+            starts,
+            targets,
+            blocked,
+            ..
+        } = scratch;
+
+        starts.clear();
+        starts.extend((0..moves.len()).map(|i| world.boop(Index(i))));
+        targets.clear();
+        targets.extend(
+            moves
+                .iter()
+                .enumerate()
+                .map(|(i, (speed, direction))| world.destination(Index(i), *speed, *direction)),
+        );
+
+        // Contenders start within `reach` and both of their moves of each other
+        let longest = moves
+            .iter()
+            .fold(0., |longest: f32, (speed, _)| longest.max(speed.abs()));
+        let contention = reach + 2. * longest;
+
+        // Blocking a boop leaves it where it started, which may block others in turn. Each round
+        // is decided as a whole so that no boop gets ahead of another by being checked first
+        loop {
+            blocked.clear();
+            blocked.extend(
+                (0..moves.len())
+                    .filter(|i| targets[*i] != starts[*i])
+                    .filter(|i| {
+                        world.boops_near(starts[*i], contention).any(|other| {
+                            let j = other.0;
+                            j != *i
+                                && targets[j].distance(targets[*i]) < reach
+                                && targets[j].distance(targets[*i]) < starts[j].distance(starts[*i])
+                        })
+                    }),
+            );
+
+            if blocked.is_empty() {
+                break;
+            }
+            for i in blocked.iter() {
+                targets[*i] = starts[*i];
+            }
+        }
+
+        for (i, target) in targets.iter().enumerate() {
+            world.move_to(Index(i), *target);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Collision, Contact, Order, Scratch};
+    use crate::sim::world::{Coordinate, Direction, World};
+    use crate::sim::{Index, Terrain};
+
+    fn pair(gap: f32) -> World {
+        World::with(
+            Terrain::open(16),
+            vec![Coordinate::new(4., 4.), Coordinate::new(4. + gap, 4.)],
+            vec![],
+        )
+    }
+
+    fn collision(contact: Contact, order: Order) -> Collision {
+        Collision {
+            radius: 0.5,
+            contact,
+            order,
+        }
+    }
+
+    #[test]
+    fn block() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let east = (1., Direction::from_rad(0.));
+        let still = (0., Direction::from_rad(0.));
+
+        for order in [Order::Random, Order::Simultaneous] {
+            let mut world = pair(1.5);
+            collision(Contact::Block, order).resolve(
+                &mut world,
+                &[east, still],
+                &mut Scratch::default(),
+                &mut rng,
+            );
+            assert_eq!(world.boop(Index(0)), Coordinate::new(4., 4.));
+
+            // Moving apart is always allowed
+            let mut world = pair(0.5);
+            let west = (1., Direction::from_rad(std::f32::consts::PI));
+            collision(Contact::Block, order).resolve(
+                &mut world,
+                &[west, still],
+                &mut Scratch::default(),
+                &mut rng,
+            );
+            assert!(world.boop(Index(0)).x() < 4.);
+        }
+    }
+
+    #[test]
+    fn simultaneous_contention() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        // Both head for the same spot, neither gets it
+        let mut world = pair(2.);
+        let east = (1., Direction::from_rad(0.));
+        let west = (1., Direction::from_rad(std::f32::consts::PI));
+        collision(Contact::Block, Order::Simultaneous).resolve(
+            &mut world,
+            &[east, west],
+            &mut Scratch::default(),
+            &mut rng,
+        );
+        assert_eq!(world.boop(Index(0)), Coordinate::new(4., 4.));
+        assert_eq!(world.boop(Index(1)), Coordinate::new(6., 4.));
+
+        // Fast bodies contend from further away
+        let mut world = pair(3.8);
+        let east = (1.9, Direction::from_rad(0.));
+        let west = (1.9, Direction::from_rad(std::f32::consts::PI));
+        collision(Contact::Block, Order::Simultaneous).resolve(
+            &mut world,
+            &[east, west],
+            &mut Scratch::default(),
+            &mut rng,
+        );
+        assert_eq!(world.boop(Index(0)), Coordinate::new(4., 4.));
+        assert!((world.boop(Index(1)).x() - 7.8).abs() < 1e-5);
+    }
+
+    #[test]
+    fn push() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let east = (1., Direction::from_rad(0.));
+        let still = (0., Direction::from_rad(0.));
+
+        let mut world = pair(1.5);
+        collision(Contact::Push, Order::Random).resolve(
+            &mut world,
+            &[east, still],
+            &mut Scratch::default(),
+            &mut rng,
+        );
+        assert_eq!(world.boop(Index(0)), Coordinate::new(5., 4.));
+        assert!((world.boop(Index(1)).x() - 6.).abs() < 1e-5);
+    }
+}
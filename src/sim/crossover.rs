@@ -0,0 +1,179 @@
+/// How the genes of two parents are combined into an offspring
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Crossover {
+    /// Every position is taken from the father with chance `bias`, or else from the mother
+    ///
+    /// Positions past the end of the shorter parent are dropped when that parent is picked
+    Uniform { bias: f32 },
+    /// The head of the father followed by the tail of the mother
+    SinglePoint,
+    /// The father with a stretch of the mother spliced in
+    TwoPoint,
+    /// Genes with the same wiring are matched up and picked from either parent, while genes
+    /// found in only one parent are each inherited with even chance
+    Aligned,
+}
+
+impl Default for Crossover {
+    fn default() -> Self {
+        Self::Uniform { bias: 0.5 }
+    }
+}
+
+impl Crossover {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Self::Uniform { bias } = self {
+            if !(0.0..=1.0).contains(bias) {
+                anyhow::bail!("Uniform bias must be between 0 and 1, got {bias}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Combines `father` and `mother`, using `key` to tell which genes are homologous
+    pub fn combine<T: Copy, K: Eq>(
+        &self,
+        father: &[T],
+        mother: &[T],
+        key: impl Fn(T) -> K,
+        rng: &mut impl rand::Rng,
+    ) -> Vec<T> {
+        let shortest = father.len().min(mother.len());
+
+        match self {
+            Self::Uniform { bias } => (0..father.len().max(mother.len()))
+                .filter_map(|i| {
+                    if rng.gen::<f32>() < *bias {
+                        father.get(i)
+                    } else {
+                        mother.get(i)
+                    }
+                })
+                .copied()
+                .collect(),
+            Self::SinglePoint => {
+                let cut = rng.gen_range(0..=shortest);
+                father[..cut]
+                    .iter()
+                    .chain(&mother[cut..])
+                    .copied()
+                    .collect()
+            }
+            Self::TwoPoint => {
+                let a = rng.gen_range(0..=shortest);
+                let b = rng.gen_range(0..=shortest);
+                let (start, end) = (a.min(b), a.max(b));
+                father[..start]
+                    .iter()
+                    .chain(&mother[start..end])
+                    .chain(&father[end..])
+                    .copied()
+                    .collect()
+            }
+            Self::Aligned => align(father, mother, key)
+                .into_iter()
+                .filter_map(|pair| match pair {
+                    (Some(f), Some(m)) => Some(if rng.gen() { f } else { m }),
+                    (Some(gene), None) | (None, Some(gene)) => rng.gen::<bool>().then_some(gene),
+                    (None, None) => None,
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Longest common subsequence alignment of the two sequences by `key`
+fn align<T: Copy, K: Eq>(
+    father: &[T],
+    mother: &[T],
+    key: impl Fn(T) -> K,
+) -> Vec<(Option<T>, Option<T>)> {
+    let width = mother.len() + 1;
+    let mut lengths = vec![0_usize; (father.len() + 1) * width];
+
+    for (i, f) in father.iter().enumerate().rev() {
+        for (j, m) in mother.iter().enumerate().rev() {
+            lengths[i * width + j] = if key(*f) == key(*m) {
+                lengths[(i + 1) * width + j + 1] + 1
+            } else {
+                lengths[(i + 1) * width + j].max(lengths[i * width + j + 1])
+            };
+        }
+    }
+
+    let mut aligned = Vec::with_capacity(father.len().max(mother.len()));
+    let (mut i, mut j) = (0, 0);
+    while i < father.len() && j < mother.len() {
+        if key(father[i]) == key(mother[j]) {
+            aligned.push((Some(father[i]), Some(mother[j])));
+            i += 1;
+            j += 1;
+        } else if lengths[(i + 1) * width + j] >= lengths[i * width + j + 1] {
+            aligned.push((Some(father[i]), None));
+            i += 1;
+        } else {
+            aligned.push((None, Some(mother[j])));
+            j += 1;
+        }
+    }
+    aligned.extend(father[i..].iter().map(|f| (Some(*f), None)));
+    aligned.extend(mother[j..].iter().map(|m| (None, Some(*m))));
+
+    aligned
+}
+
+#[cfg(test)]
+mod test {
+    use super::Crossover;
+    use rand::SeedableRng;
+
+    #[test]
+    fn single_and_two_point() {
+        let father = [1, 2, 3, 4, 5, 6];
+        let mother = [-1, -2, -3];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        for _ in 0..32 {
+            let child = Crossover::SinglePoint.combine(&father, &mother, |g| g, &mut rng);
+            assert_eq!(child.len(), mother.len());
+            let cut = child.iter().take_while(|g| **g > 0).count();
+            assert!(child[cut..].iter().all(|g| *g < 0));
+
+            let child = Crossover::TwoPoint.combine(&father, &mother, |g| g, &mut rng);
+            assert_eq!(child.len(), father.len());
+            assert_eq!(&child[3..], &father[3..]);
+        }
+    }
+
+    #[test]
+    fn uniform_bias() {
+        let father = [1; 8];
+        let mother = [2; 4];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let child = Crossover::Uniform { bias: 1. }.combine(&father, &mother, |g| g, &mut rng);
+        assert_eq!(child, father);
+
+        let child = Crossover::Uniform { bias: 0. }.combine(&father, &mother, |g| g, &mut rng);
+        assert_eq!(child, mother);
+    }
+
+    #[test]
+    fn aligned() {
+        let father = [(1, 'f'), (2, 'f'), (3, 'f'), (5, 'f')];
+        let mother = [(1, 'm'), (3, 'm'), (4, 'm'), (5, 'm')];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        for _ in 0..32 {
+            let child = Crossover::Aligned.combine(&father, &mother, |(k, _)| k, &mut rng);
+            let keys = child.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+
+            // Shared genes are always inherited, in order
+            for shared in [1, 3, 5] {
+                assert_eq!(keys.iter().filter(|k| **k == shared).count(), 1);
+            }
+            assert!(keys.is_sorted());
+        }
+    }
+}
@@ -0,0 +1,250 @@
+use super::terrain::Terrain;
+use super::world::Coordinate;
+
+/// How food is eaten and replenished during a generation
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Forage {
+    /// Food is gone once a boop reaches it, instead of feeding whoever is nearby every day
+    pub consumed: bool,
+    /// Chance per day for every missing piece of food to grow back
+    pub respawn: f32,
+    /// Where new food appears
+    pub spawn: Spawn,
+    /// Food spreading from the food already there
+    pub growth: Option<Growth>,
+}
+
+/// Where food is placed, at the start of a generation and whenever it grows back
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Spawn {
+    /// Anywhere on open ground
+    #[default]
+    Uniform,
+    /// Within `spread` of one of `clusters` spots, picked anew for every generation
+    Clustered { clusters: usize, spread: f32 },
+    /// Within `spread` of a spot circling the world once every `period` days
+    Seasonal { period: usize, spread: f32 },
+}
+
+/// Patches of food growing over time
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Growth {
+    /// Chance per day for every piece of food to sprout another one
+    pub rate: f32,
+    /// Farthest a sprout lands from the food it grew from
+    pub spread: f32,
+    /// Most food the world can hold
+    pub limit: usize,
+}
+
+impl Default for Growth {
+    fn default() -> Self {
+        Self {
+            rate: 0.05,
+            spread: 2.,
+            limit: 64,
+        }
+    }
+}
+
+impl Forage {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if !(0.0..=1.0).contains(&self.respawn) {
+            anyhow::bail!(
+                "Respawn chance must be between 0 and 1, got {}",
+                self.respawn
+            );
+        }
+
+        match self.spawn {
+            Spawn::Uniform => {}
+            Spawn::Clustered { clusters, spread } => {
+                if clusters == 0 {
+                    anyhow::bail!("Clustered spawning needs at least 1 cluster");
+                }
+                validate_spread(spread)?;
+            }
+            Spawn::Seasonal { period, spread } => {
+                if period == 0 {
+                    anyhow::bail!("Seasonal period must be at least 1 day");
+                }
+                validate_spread(spread)?;
+            }
+        }
+
+        if let Some(growth) = &self.growth {
+            if !(0.0..=1.0).contains(&growth.rate) {
+                anyhow::bail!("Growth rate must be between 0 and 1, got {}", growth.rate);
+            }
+            validate_spread(growth.spread)?;
+            if growth.limit == 0 {
+                anyhow::bail!("Growth limit must be at least 1");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Spots that food clusters around for a whole generation
+    pub fn centres(&self, terrain: &Terrain, rng: &mut impl rand::Rng) -> Vec<Coordinate> {
+        match self.spawn {
+            Spawn::Clustered { clusters, .. } => build_vec!(|| terrain.spot(rng), clusters),
+            Spawn::Uniform | Spawn::Seasonal { .. } => Vec::new(),
+        }
+    }
+
+    /// Where a new piece of food appears on `day`
+    pub fn place(
+        &self,
+        terrain: &Terrain,
+        centres: &[Coordinate],
+        day: usize,
+        rng: &mut impl rand::Rng,
+    ) -> Coordinate {
+        match self.spawn {
+            Spawn::Clustered { spread, .. } if !centres.is_empty() => {
+                let centre = centres[rng.gen_range(0..centres.len())];
+                around(terrain, centre, spread, rng)
+            }
+            Spawn::Seasonal { period, spread } => {
+                let middle = f32::from(terrain.size() - 1) / 2.;
+
+                // ALLOWED: Only the phase within a period matters
+                #[allow(clippy::cast_precision_loss)]
+                let angle = (day % period) as f32 / period as f32 * std::f32::consts::TAU;
+                let centre = Coordinate::new(
+                    middle + middle / 2. * angle.cos(),
+                    middle + middle / 2. * angle.sin(),
+                );
+                around(terrain, centre, spread, rng)
+            }
+            _ => terrain.spot(rng),
+        }
+    }
+}
+
+fn validate_spread(spread: f32) -> anyhow::Result<()> {
+    if spread.is_finite() && spread > 0. {
+        Ok(())
+    } else {
+        anyhow::bail!("Spread must be positive, got {spread}")
+    }
+}
+
+/// Random spot on open ground within `spread` of `centre`
+///
+/// Falls back to anywhere on open ground when the surroundings are mostly walls or lava
+pub fn around(
+    terrain: &Terrain,
+    centre: Coordinate,
+    spread: f32,
+    rng: &mut impl rand::Rng,
+) -> Coordinate {
+    const ATTEMPTS: usize = 16;
+
+    let max = f32::from(terrain.size() - 1);
+    for _ in 0..ATTEMPTS {
+        let angle = rng.gen::<f32>() * std::f32::consts::TAU;
+        let distance = spread * rng.gen::<f32>().sqrt();
+        let coord = Coordinate::new(
+            (centre.x() + distance * angle.cos()).clamp(0., max),
+            (centre.y() + distance * angle.sin()).clamp(0., max),
+        );
+        if terrain.is_open(coord) {
+            return coord;
+        }
+    }
+    terrain.spot(rng)
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Forage, Growth, Spawn};
+    use crate::sim::terrain::Terrain;
+    use crate::sim::world::{Coordinate, World};
+    use crate::sim::Index;
+
+    #[test]
+    fn consumed() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let forage = Forage {
+            consumed: true,
+            ..Forage::default()
+        };
+        let mut world = World::with(
+            Terrain::open(16),
+            vec![Coordinate::new(4., 4.), Coordinate::new(4.6, 4.)],
+            vec![Coordinate::new(4.5, 4.), Coordinate::new(12., 12.)],
+        );
+
+        // Only the closest boop gets to eat
+        world.graze(&forage, 0.);
+        assert_eq!(world.fodder().count(), 1);
+        assert_eq!(world.record(Index(0)).eaten, 0);
+        assert_eq!(world.record(Index(1)).eaten, 1);
+
+        world.graze(&forage, 0.);
+        assert_eq!(world.record(Index(1)).eaten, 1);
+
+        // Eaten food grows back
+        let forage = Forage {
+            respawn: 1.,
+            ..forage
+        };
+        world.replenish(2, &forage, &mut rng);
+        assert_eq!(world.fodder().count(), 2);
+    }
+
+    #[test]
+    fn growth() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let forage = Forage {
+            growth: Some(Growth {
+                rate: 1.,
+                spread: 2.,
+                limit: 10,
+            }),
+            ..Forage::default()
+        };
+        let seed = Coordinate::new(8., 8.);
+        let mut world = World::with(Terrain::open(32), vec![], vec![seed]);
+
+        world.replenish(1, &forage, &mut rng);
+        assert_eq!(world.fodder().count(), 2);
+        assert!(world.fodder().all(|food| food.distance(seed) <= 2.));
+
+        for _ in 0..8 {
+            world.replenish(1, &forage, &mut rng);
+        }
+        assert_eq!(world.fodder().count(), 10);
+    }
+
+    #[test]
+    fn clustered() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let terrain = Terrain::open(64);
+        let forage = Forage {
+            spawn: Spawn::Clustered {
+                clusters: 2,
+                spread: 3.,
+            },
+            ..Forage::default()
+        };
+        let centres = forage.centres(&terrain, &mut rng);
+        assert_eq!(centres.len(), 2);
+
+        for _ in 0..64 {
+            let food = forage.place(&terrain, &centres, 0, &mut rng);
+            assert!(centres.iter().any(|centre| centre.distance(food) <= 3.));
+        }
+    }
+}
@@ -0,0 +1,160 @@
+use super::world::Coordinate;
+
+/// Uniform grid bucketing points by the cell they fall in
+///
+/// The grid only holds indices into a slice of points kept by its owner, which must pass that
+/// same slice to every query. Points are bucketed with a counting sort, so rebuilding reuses the
+/// buffers and queries never allocate
+pub struct Grid {
+    cell: f32,
+    columns: usize,
+    /// Position in `items` where each cell starts, with one extra entry closing the last cell
+    starts: Vec<usize>,
+    items: Vec<usize>,
+}
+
+impl Grid {
+    /// Grid over coordinates in `0..=extent`, sized for about one of `count` points per cell
+    pub fn new(extent: f32, count: usize) -> Self {
+        // ALLOWED: Only a rough cell size is needed, and the world is at most 255 wide
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let (cell, columns) = {
+            let cell = (extent / (count.max(1) as f32).sqrt()).max(1.);
+            (cell, (extent / cell) as usize + 1)
+        };
+
+        Self {
+            cell,
+            columns,
+            starts: vec![0; columns * columns + 1],
+            items: Vec::new(),
+        }
+    }
+
+    pub fn rebuild(&mut self, points: &[Coordinate]) {
+        self.starts.fill(0);
+        for point in points {
+            let cell = self.cell_of(*point);
+            self.starts[cell] += 1;
+        }
+        for i in 1..self.starts.len() {
+            self.starts[i] += self.starts[i - 1];
+        }
+
+        // Every cell is filled from its end, which leaves its start behind
+        self.items.clear();
+        self.items.resize(points.len(), 0);
+        for (index, point) in points.iter().enumerate().rev() {
+            let cell = self.cell_of(*point);
+            self.starts[cell] -= 1;
+            self.items[self.starts[cell]] = index;
+        }
+    }
+
+    /// Indices of the points in every cell overlapping `center ± radius`, which includes all the
+    /// ones within `radius` of `center`, for callers measuring distances themselves
+    pub fn candidates(&self, center: Coordinate, radius: f32) -> impl Iterator<Item = usize> + '_ {
+        let (x0, x1) = self.span(center.x(), radius);
+        let (y0, y1) = self.span(center.y(), radius);
+
+        (y0..=y1)
+            .flat_map(move |y| {
+                let row = y * self.columns;
+                &self.items[self.starts[row + x0]..self.starts[row + x1 + 1]]
+            })
+            .copied()
+    }
+
+    /// Index of the point closest to `center` and its distance
+    pub fn nearest(&self, points: &[Coordinate], center: Coordinate) -> Option<(usize, f32)> {
+        let (cx, cy) = (self.column(center.x()), self.column(center.y()));
+        let mut best: Option<(usize, f32)> = None;
+
+        for ring in 0..self.columns {
+            let (x0, x1) = (cx.saturating_sub(ring), (cx + ring).min(self.columns - 1));
+            let (y0, y1) = (cy.saturating_sub(ring), (cy + ring).min(self.columns - 1));
+
+            // Only the outline of the ring is new, the inside was searched already
+            for y in y0..=y1 {
+                let edge = y + ring == cy || y == cy + ring;
+                let columns = (x0..=x1).filter(|x| edge || x + ring == cx || *x == cx + ring);
+                for x in columns {
+                    let cell = y * self.columns + x;
+                    for i in &self.items[self.starts[cell]..self.starts[cell + 1]] {
+                        let distance = points[*i].distance(center);
+                        if best.is_none_or(|(_, d)| distance < d) {
+                            best = Some((*i, distance));
+                        }
+                    }
+                }
+            }
+
+            // ALLOWED: Ring is at most 255
+            #[allow(clippy::cast_precision_loss)]
+            if best.is_some_and(|(_, d)| d <= ring as f32 * self.cell) {
+                break;
+            }
+        }
+
+        best
+    }
+
+    /// First and last column overlapped by `value ± radius`
+    fn span(&self, value: f32, radius: f32) -> (usize, usize) {
+        (self.column(value - radius), self.column(value + radius))
+    }
+
+    // ALLOWED: Negative values saturate to zero and the result is clamped to the grid
+    #[allow(clippy::cast_possible_truncation, clippy::cast_sign_loss)]
+    fn column(&self, value: f32) -> usize {
+        ((value / self.cell) as usize).min(self.columns - 1)
+    }
+
+    fn cell_of(&self, point: Coordinate) -> usize {
+        self.column(point.y()) * self.columns + self.column(point.x())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Grid;
+    use crate::sim::world::Coordinate;
+
+    #[test]
+    fn matches_brute_force() {
+        use rand::{Rng, SeedableRng};
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+        let mut random = || Coordinate::new(rng.gen::<f32>() * 63., rng.gen::<f32>() * 63.);
+
+        for count in [0, 1, 7, 300] {
+            let points = (0..count).map(|_| random()).collect::<Vec<_>>();
+            let mut grid = Grid::new(63., count);
+            grid.rebuild(&points);
+
+            for _ in 0..64 {
+                let center = random();
+
+                let closest = points
+                    .iter()
+                    .map(|p| p.distance(center))
+                    .min_by(f32::total_cmp);
+                assert_eq!(grid.nearest(&points, center).map(|(_, d)| d), closest);
+
+                let mut within = grid
+                    .candidates(center, 5.)
+                    .filter(|i| points[*i].distance(center) <= 5.)
+                    .collect::<Vec<_>>();
+                within.sort_unstable();
+                let expected = (0..count)
+                    .filter(|i| points[*i].distance(center) <= 5.)
+                    .collect::<Vec<_>>();
+                assert_eq!(within, expected);
+            }
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use super::boop::{Boop, Intent};
+
+/// Energy boops burn by living and acting, and regain by eating
+///
+/// Every boop starts a generation with a full tank of 1, and dies once it runs dry
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Metabolism {
+    /// Spent every day just staying alive
+    pub basal: f32,
+    /// Spent for every unit moved, as far as the boop got of the distance it asked for
+    ///
+    /// Running into a wall or another boop costs nothing, and neither does being shoved
+    pub movement: f32,
+    /// Spent for every radian of turn asked for, in either direction
+    ///
+    /// Turning left and right at once costs both, even though they cancel out
+    pub turning: f32,
+    /// Restored by every meal, up to a full tank
+    pub meal: f32,
+}
+
+impl Default for Metabolism {
+    fn default() -> Self {
+        Self {
+            basal: 0.001,
+            movement: 0.01,
+            turning: 0.005,
+            meal: 0.2,
+        }
+    }
+}
+
+impl Metabolism {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, value) in [
+            ("basal", self.basal),
+            ("movement", self.movement),
+            ("turning", self.turning),
+            ("meal", self.meal),
+        ] {
+            if !value.is_finite() || value < 0. {
+                anyhow::bail!("`{name}` must not be negative, got {value}");
+            }
+        }
+        Ok(())
+    }
+
+    /// Energy a day spent acting on `intent` costs `boop`, which `travelled` that far
+    ///
+    /// Bigger bodies are heavier to move, keen senses are costly to keep up, and faster bodies
+    /// burn more for the same effort as they cover more ground
+    pub fn cost(&self, intent: Intent, travelled: f32, boop: &Boop) -> f32 {
+        let moved = travelled.min(intent.advance.abs());
+        let effort = self.movement * moved + self.turning * intent.steering;
+        self.basal * boop.size() * boop.sensing() + effort * boop.size()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Metabolism;
+    use crate::sim::boop::{Boop, Intent};
+    use crate::sim::world::{Coordinate, Direction, World};
+    use crate::sim::{Index, Terrain};
+
+    #[test]
+    fn into_a_wall() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        let boop = Boop::new(4, 0, &mut rng);
+        let terrain = Terrain::parse("....\n.#..\n....\n....\n").unwrap();
+        let mut world = World::with(terrain, vec![Coordinate::new(0., 1.)], vec![]);
+        let metabolism = Metabolism::default();
+        let idle = metabolism.cost(Intent::default(), 0., &boop);
+
+        let east = Intent {
+            advance: 1.,
+            ..Intent::default()
+        };
+        world.advance(Index(0), east.advance, Direction::from_rad(0.));
+        assert_eq!(world.boop(Index(0)), Coordinate::new(0., 1.));
+        let cost = metabolism.cost(east, world.moved(Index(0)), &boop);
+        assert!((cost - idle).abs() < f32::EPSILON);
+
+        world.reindex();
+        world.advance(
+            Index(0),
+            east.advance,
+            Direction::from_rad(std::f32::consts::FRAC_PI_2),
+        );
+        let cost = metabolism.cost(east, world.moved(Index(0)), &boop);
+        assert!((cost - idle - metabolism.movement * boop.size()).abs() < 1e-6);
+    }
+}
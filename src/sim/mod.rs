@@ -1,68 +1,407 @@
 mod boop;
+mod collision;
+mod crossover;
+mod food;
+mod grid;
+mod metabolism;
+mod mutation;
+mod population;
+mod reproduction;
+mod selection;
+mod snapshot;
+mod statistics;
+mod terrain;
 mod world;
 
-use boop::Boop;
+use boop::{Boop, Intent, Minds};
+use snapshot::{Reader, Writer};
 use world::World;
 
+pub use boop::{Neural, MAX_HIDDEN_NEURONS};
+pub use collision::Collision;
+pub use crossover::Crossover;
+pub use food::Forage;
+pub use metabolism::Metabolism;
+pub use mutation::{Mutation, Mutations};
+pub use population::Population;
+pub use reproduction::Reproduction;
+pub use selection::Selection;
+pub use statistics::Statistics;
+pub use terrain::{Hazard, Landscape, Terrain, Tile};
 pub use world::{Coordinate, Direction};
 
+type Rng = rand::rngs::StdRng;
+
 pub struct Simulation {
     world: World,
     boops: Vec<Boop>,
+    minds: Minds,
     hidden_neurons: u8,
+    parameters: Parameters,
+    generation: usize,
+    mutations: Mutations,
+    survivors: usize,
+    seed: u64,
+    rng: Rng,
+    /// Scratch buffers of [`Self::step`], kept to avoid allocating every day
+    noise: Vec<f32>,
+    intents: Vec<Intent>,
+    moves: Vec<(f32, Direction)>,
+    collisions: collision::Scratch,
 }
 
-impl Simulation {
-    pub fn new(size: u8, boops: usize, synapses: u16, hidden_neurons: u8) -> Self {
+/// Rules of the simulation that are not part of its state
+///
+/// These are not persisted in snapshots, so a resumed run may change them
+pub struct Parameters {
+    /// Amount of food placed in the world of every generation
+    pub food: usize,
+    /// How food is eaten and grows back
+    pub forage: Forage,
+    /// Obstacles of a new world, snapshots carry their own
+    pub landscape: Landscape,
+    /// What lava does to the boops standing in it
+    pub hazard: Hazard,
+    /// Whether boops burn energy and starve without food
+    pub metabolism: Option<Metabolism>,
+    /// Whether boops bump into each other instead of passing through
+    pub collision: Option<Collision>,
+    /// Activation functions of the neurons in every brain
+    pub neural: Neural,
+    /// How the genes of two parents are combined
+    pub crossover: Crossover,
+    /// How offspring deviate from their parents
+    pub mutation: Mutation,
+    /// Who gets to reproduce at the end of a generation
+    pub selection: Selection,
+    /// How the survivors produce the next generation
+    pub reproduction: Reproduction,
+}
+
+impl Default for Parameters {
+    fn default() -> Self {
         Self {
-            world: World::new(size, boops, 4),
-            boops: build_vec!(|| Boop::new(synapses, hidden_neurons), boops),
+            food: 4,
+            forage: Forage::default(),
+            landscape: Landscape::default(),
+            hazard: Hazard::default(),
+            metabolism: None,
+            collision: None,
+            neural: Neural::default(),
+            crossover: Crossover::default(),
+            mutation: Mutation::default(),
+            selection: Selection::default(),
+            reproduction: Reproduction::default(),
+        }
+    }
+}
+
+/// Where the genomes of the first generation come from
+pub enum Genesis {
+    Random { synapses: u16, hidden_neurons: u8 },
+    Population(Population),
+}
+
+impl Simulation {
+    pub fn new(
+        seed: u64,
+        size: u8,
+        boops: usize,
+        genesis: Genesis,
+        parameters: Parameters,
+    ) -> Self {
+        use rand::SeedableRng;
+
+        let mut rng = Rng::seed_from_u64(seed);
+        let terrain = parameters.landscape.build(size, &mut rng);
+        let world = World::new(
+            terrain,
+            boops,
+            parameters.food,
+            &parameters.forage,
+            &mut rng,
+        );
+
+        let (boops, hidden_neurons): (Vec<Boop>, _) = match genesis {
+            Genesis::Random {
+                synapses,
+                hidden_neurons,
+            } => (
+                build_vec!(|| Boop::new(synapses, hidden_neurons, &mut rng), boops),
+                hidden_neurons,
+            ),
+            Genesis::Population(population) => (
+                population
+                    .genomes()
+                    .iter()
+                    .cycle()
+                    .take(boops)
+                    .map(|genome| Boop::from_genome(genome.clone(), &mut rng))
+                    .collect(),
+                population.hidden_neurons(),
+            ),
+        };
+
+        let mut simulation = Self {
+            minds: Minds::new(boops.iter().map(Boop::genome), parameters.neural),
+            world,
+            boops,
             hidden_neurons,
+            parameters,
+            generation: 0,
+            mutations: Mutations::default(),
+            survivors: 0,
+            seed,
+            rng,
+            noise: Vec::new(),
+            intents: Vec::new(),
+            moves: Vec::new(),
+            collisions: collision::Scratch::default(),
+        };
+        simulation.embody();
+        simulation
+    }
+
+    /// Lets the world know the bodies of the boops living in it
+    fn embody(&mut self) {
+        for (i, boop) in self.boops.iter().enumerate() {
+            self.world.embody(Index(i), boop.size(), boop.signature());
         }
     }
 
+    /// Advances the world by a day
+    ///
+    /// Every brain first decides against the same, untouched world, in parallel. Only then are
+    /// the decisions applied, so no boop sees the outcome of another one's move of the same day
+    ///
+    /// Dead boops stay where they died, be it in lava or of exhaustion, but no longer act, eat,
+    /// nor get in anyone's way
     pub fn step(&mut self) {
-        // TODO: The sequence of actions may interfer with each other
-        for index in 0..self.boops.len() {
-            // TODO: If an action kills, this loop must be careful
-            // SAFETY: `boop` does not get moved or dropped
-            let boop: *mut Boop = self.boop_mut(Index(index));
-            unsafe { (*boop).mind_mut().react(self, Index(index)) };
+        use rand::Rng as _;
+
+        // Drawn up front, in order, so that threads never race for the random stream
+        self.noise.clear();
+        self.noise
+            .extend((0..self.boops.len()).map(|_| self.rng.gen::<f32>()));
+
+        self.minds
+            .react(&self.boops, &self.world, &self.noise, &mut self.intents);
+
+        for (boop, intent) in self.boops.iter_mut().zip(&self.intents) {
+            boop.turn(intent.turn);
+        }
+
+        if let Some(collision) = &self.parameters.collision {
+            self.moves.clear();
+            self.moves.extend(
+                self.boops
+                    .iter()
+                    .zip(&self.intents)
+                    .map(|(boop, intent)| (intent.advance, boop.direction())),
+            );
+            collision.resolve(
+                &mut self.world,
+                &self.moves,
+                &mut self.collisions,
+                &mut self.rng,
+            );
+        } else {
+            for (i, (boop, intent)) in self.boops.iter().zip(&self.intents).enumerate() {
+                self.world
+                    .advance(Index(i), intent.advance, boop.direction());
+            }
+        }
+
+        if let Some(metabolism) = &self.parameters.metabolism {
+            for (i, intent) in self.intents.iter().enumerate() {
+                let travelled = self.world.moved(Index(i));
+                self.world.exert(
+                    Index(i),
+                    metabolism.cost(*intent, travelled, &self.boops[i]),
+                );
+            }
         }
+
+        self.world.scorch(self.parameters.hazard.damage);
+        self.world.reindex();
+        self.world.graze(
+            &self.parameters.forage,
+            self.parameters.metabolism.as_ref().map_or(0., |m| m.meal),
+        );
+        self.world
+            .replenish(self.parameters.food, &self.parameters.forage, &mut self.rng);
     }
 
-    pub fn next_generation(&mut self) -> bool {
+    /// Spawns the next generation from the survivors of the current one
+    ///
+    /// Only boops still alive are considered. Returns the number of survivors, if none survived
+    /// the population is extinct and the simulation is left untouched
+    pub fn next_generation(&mut self) -> usize {
         use rand::seq::SliceRandom;
 
         let count = self.boops.len();
 
-        let mut survivors = (0..count)
-            .filter(|i| self.world.on_food(Index(*i)))
-            .collect::<Vec<_>>();
+        let alive = (0..count).filter(|i| self.world.alive(Index(*i))).collect();
+        let mut survivors = self.parameters.selection.select(&self.world, alive);
 
         if survivors.is_empty() {
-            return false;
+            return 0;
         }
 
-        let mut rng = rand::thread_rng();
-        survivors.shuffle(&mut rng);
+        survivors.shuffle(&mut self.rng);
 
+        let reproduction = &self.parameters.reproduction;
         let mut spawn = Vec::with_capacity(count);
 
-        for _ in 0..count {
-            let father = *survivors.choose(&mut rng).unwrap();
-            let mother = *survivors.choose(&mut rng).unwrap();
+        for elite in reproduction
+            .elite(&self.world, &survivors)
+            .into_iter()
+            .take(count)
+        {
+            spawn.push(Boop::from_genome(
+                self.boops[elite].genome().clone(),
+                &mut self.rng,
+            ));
+        }
 
-            spawn.push(self.boop(Index(father)).mate(
-                self.boop(Index(mother)),
-                0.001,
+        let mut mutations = Mutations::default();
+        let parents = reproduction.parents(&self.world, &survivors);
+        while spawn.len() < count {
+            let (father, mother) = parents.pick(&mut self.rng);
+
+            let (boop, mutated) = self.boops[father].mate(
+                &self.boops[mother],
+                &self.parameters.crossover,
+                &self.parameters.mutation,
                 self.hidden_neurons,
-            ));
+                &mut self.rng,
+            );
+            spawn.push(boop);
+            mutations += mutated;
         }
 
-        self.world = World::new(self.size(), count, 4);
+        self.world = World::new(
+            self.world.terrain().clone(),
+            count,
+            self.parameters.food,
+            &self.parameters.forage,
+            &mut self.rng,
+        );
+        self.minds = Minds::new(spawn.iter().map(Boop::genome), self.parameters.neural);
         self.boops = spawn;
-        true
+        self.embody();
+        self.mutations = mutations;
+        self.survivors = survivors.len();
+        self.generation += 1;
+        self.reseed();
+        survivors.len()
+    }
+
+    /// Restarts the random stream from a fresh seed drawn from the current one
+    ///
+    /// Done at every generation boundary so that a snapshot only needs to carry the seed to
+    /// resume bit-identically
+    fn reseed(&mut self) {
+        use rand::{Rng as _, SeedableRng};
+
+        self.seed = self.rng.gen();
+        self.rng = Rng::seed_from_u64(self.seed);
+    }
+
+    /// Writes the full state of the simulation
+    ///
+    /// The random stream is only captured as of the start of the current generation, so
+    /// snapshots should be taken right after [`Self::next_generation`] to resume bit-identically
+    pub fn save(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        snapshot::write_header(writer)?;
+
+        writer.write_usize(self.generation)?;
+        writer.write_u64(self.seed)?;
+        writer.write_u8(self.hidden_neurons)?;
+
+        self.world.save(writer)?;
+
+        writer.write_usize(self.boops.len())?;
+        for boop in &self.boops {
+            boop.save(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(reader: &mut impl std::io::Read, parameters: Parameters) -> anyhow::Result<Self> {
+        use rand::SeedableRng;
+
+        snapshot::read_header(reader)?;
+
+        let generation = reader.read_usize()?;
+        let seed = reader.read_u64()?;
+        let hidden_neurons = reader.read_u8()?;
+        if hidden_neurons > MAX_HIDDEN_NEURONS {
+            anyhow::bail!(
+                "At most {MAX_HIDDEN_NEURONS} hidden neurons are supported, got {hidden_neurons}"
+            );
+        }
+
+        let world = World::load(reader)?;
+
+        let count = reader.read_usize()?;
+        let boops = (0..count)
+            .map(|_| Boop::load(reader, hidden_neurons))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if world.population() != boops.len() {
+            anyhow::bail!(
+                "World has {} boop coordinates but the snapshot has {} boops",
+                world.population(),
+                boops.len()
+            );
+        }
+
+        let mut simulation = Self {
+            minds: Minds::new(boops.iter().map(Boop::genome), parameters.neural),
+            world,
+            boops,
+            hidden_neurons,
+            parameters,
+            generation,
+            mutations: Mutations::default(),
+            survivors: 0,
+            seed,
+            rng: Rng::seed_from_u64(seed),
+            noise: Vec::new(),
+            intents: Vec::new(),
+            moves: Vec::new(),
+            collisions: collision::Scratch::default(),
+        };
+        simulation.embody();
+        Ok(simulation)
+    }
+
+    /// Writes the gene pool of the current generation
+    pub fn export(&self, writer: &mut impl std::io::Write) -> std::io::Result<()> {
+        Population::write(
+            writer,
+            self.hidden_neurons,
+            self.boops.iter().map(Boop::genome),
+        )
+    }
+
+    #[inline]
+    pub fn generation(&self) -> usize {
+        self.generation
+    }
+
+    /// Mutations carried by the current generation
+    #[inline]
+    pub fn mutations(&self) -> Mutations {
+        self.mutations
+    }
+
+    /// Survivors of the previous generation, the parents of the current one
+    #[inline]
+    pub fn survivors(&self) -> usize {
+        self.survivors
     }
 
     #[inline]
@@ -70,38 +409,43 @@ impl Simulation {
         self.world.size()
     }
 
+    #[inline]
+    pub fn terrain(&self) -> &Terrain {
+        self.world.terrain()
+    }
+
     #[inline]
     pub fn fodder(&self) -> impl Iterator<Item = &Coordinate> {
         self.world.fodder()
     }
 
+    #[inline]
+    pub fn population(&self) -> usize {
+        self.boops.len()
+    }
+
     pub fn boops(&self) -> impl Iterator<Item = Accessor<'_>> {
         self.boops
             .iter()
             .enumerate()
-            .map(|(i, b)| Accessor(b, self.world.boop(Index(i))))
-    }
-
-    #[inline]
-    fn boop(&self, index: Index) -> &Boop {
-        unsafe { self.boops.get_unchecked(index.0) }
-    }
-
-    #[inline]
-    fn boop_mut(&mut self, index: Index) -> &mut Boop {
-        unsafe { self.boops.get_unchecked_mut(index.0) }
+            .map(|(i, b)| Accessor(b, self.world.boop(Index(i)), self.world.alive(Index(i))))
     }
 }
 
 #[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Debug)]
 pub struct Index(usize);
 
-pub struct Accessor<'a>(&'a Boop, Coordinate);
+pub struct Accessor<'a>(&'a Boop, Coordinate, bool);
 
 impl Accessor<'_> {
     pub fn coordinate(&self) -> Coordinate {
         self.1
     }
+
+    /// Whether the boop has neither died in lava nor run out of energy yet
+    pub fn alive(&self) -> bool {
+        self.2
+    }
 }
 
 impl std::ops::Deref for Accessor<'_> {
@@ -111,3 +455,214 @@ impl std::ops::Deref for Accessor<'_> {
         self.0
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::{Genesis, Hazard, Landscape, Metabolism, Parameters, Population, Simulation, Tile};
+
+    fn random() -> Genesis {
+        Genesis::Random {
+            synapses: 8,
+            hidden_neurons: 4,
+        }
+    }
+
+    fn snapshot(simulation: &Simulation) -> Vec<(u32, f32, f32, f32)> {
+        simulation
+            .boops()
+            .map(|b| {
+                let coord = b.coordinate();
+                (b.signature(), coord.x(), coord.y(), b.direction().as_rad())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn deterministic() {
+        let mut a = Simulation::new(27, 32, 64, random(), Parameters::default());
+        let mut b = Simulation::new(27, 32, 64, random(), Parameters::default());
+
+        for _ in 0..3 {
+            for _ in 0..16 {
+                a.step();
+                b.step();
+                assert_eq!(snapshot(&a), snapshot(&b));
+            }
+
+            let survivors = a.next_generation();
+            assert_eq!(survivors, b.next_generation());
+            assert_eq!(snapshot(&a), snapshot(&b));
+
+            if survivors == 0 {
+                break;
+            }
+        }
+    }
+
+    #[test]
+    fn thread_count_independent() {
+        let mut a = Simulation::new(27, 16, 512, random(), Parameters::default());
+        let mut b = Simulation::new(27, 16, 512, random(), Parameters::default());
+        let single = rayon::ThreadPoolBuilder::new()
+            .num_threads(1)
+            .build()
+            .unwrap();
+
+        for _ in 0..16 {
+            a.step();
+            single.install(|| b.step());
+            assert_eq!(snapshot(&a), snapshot(&b));
+        }
+    }
+
+    #[test]
+    fn resume() {
+        let mut a = Simulation::new(27, 8, 64, random(), Parameters::default());
+        for _ in 0..16 {
+            a.step();
+        }
+        assert_ne!(a.next_generation(), 0);
+
+        let mut buffer = vec![];
+        a.save(&mut buffer).unwrap();
+        let mut b = Simulation::load(&mut buffer.as_slice(), Parameters::default()).unwrap();
+        assert_eq!(a.generation(), b.generation());
+        assert_eq!(snapshot(&a), snapshot(&b));
+
+        for _ in 0..16 {
+            a.step();
+            b.step();
+        }
+        assert_eq!(a.next_generation(), b.next_generation());
+        assert_eq!(snapshot(&a), snapshot(&b));
+    }
+
+    #[test]
+    fn terrain_persists() {
+        let parameters = || Parameters {
+            landscape: Landscape::Scattered {
+                density: 0.3,
+                lava: 0.,
+            },
+            ..Parameters::default()
+        };
+        let mut a = Simulation::new(27, 16, 64, random(), parameters());
+        assert!(a.terrain().tiles(Tile::Wall).count() > 0);
+        assert!(a.boops().all(|b| a.terrain().is_open(b.coordinate())));
+
+        for _ in 0..16 {
+            a.step();
+            assert!(a.boops().all(|b| a.terrain().is_open(b.coordinate())));
+        }
+        let terrain = a.terrain().clone();
+        if a.next_generation() > 0 {
+            assert_eq!(a.terrain(), &terrain);
+        }
+
+        let mut buffer = vec![];
+        a.save(&mut buffer).unwrap();
+        let b = Simulation::load(&mut buffer.as_slice(), Parameters::default()).unwrap();
+        assert_eq!(b.terrain(), &terrain);
+    }
+
+    #[test]
+    fn lava() {
+        let parameters = |damage| Parameters {
+            landscape: Landscape::Scattered {
+                density: 0.,
+                lava: 0.5,
+            },
+            hazard: Hazard { damage },
+            ..Parameters::default()
+        };
+
+        // Harmless lava leaves everyone alive
+        let mut a = Simulation::new(27, 16, 256, random(), parameters(0.));
+        for _ in 0..16 {
+            a.step();
+        }
+        assert!(a.boops().all(|b| b.alive()));
+
+        let mut a = Simulation::new(27, 16, 256, random(), parameters(1.));
+        let mut dead = Vec::new();
+        for _ in 0..16 {
+            a.step();
+
+            // The dead stay dead, and where they died
+            let now = a
+                .boops()
+                .enumerate()
+                .filter(|(_, b)| !b.alive())
+                .map(|(i, b)| (i, b.coordinate()))
+                .collect::<Vec<_>>();
+            assert!(dead.iter().all(|d| now.contains(d)));
+            assert!(now.iter().all(|(_, c)| a.terrain().at(*c) == Tile::Lava));
+            dead = now;
+        }
+        assert!(!dead.is_empty());
+    }
+
+    #[test]
+    fn starvation() {
+        let parameters = Parameters {
+            metabolism: Some(Metabolism {
+                basal: 0.125,
+                movement: 0.,
+                turning: 0.,
+                meal: 0.,
+            }),
+            ..Parameters::default()
+        };
+        let mut a = Simulation::new(27, 16, 64, random(), parameters);
+
+        for _ in 0..7 {
+            a.step();
+        }
+        assert!(a.boops().all(|b| b.alive()));
+
+        a.step();
+        assert!(a.boops().all(|b| !b.alive()));
+        assert_eq!(a.next_generation(), 0);
+    }
+
+    #[test]
+    fn reject_foreign_snapshot() {
+        assert!(Simulation::load(&mut b"NOPE\x01\x00".as_slice(), Parameters::default()).is_err());
+    }
+
+    #[test]
+    fn seeded() {
+        let a = Simulation::new(27, 32, 64, random(), Parameters::default());
+        let b = Simulation::new(72, 32, 64, random(), Parameters::default());
+        assert_ne!(snapshot(&a), snapshot(&b));
+    }
+
+    #[test]
+    fn transplant() {
+        let a = Simulation::new(27, 8, 64, random(), Parameters::default());
+
+        let mut buffer = vec![];
+        a.export(&mut buffer).unwrap();
+        let population = Population::read(buffer.as_slice()).unwrap();
+        assert_eq!(population.genomes().len(), 64);
+
+        let b = Simulation::new(
+            27,
+            16,
+            128,
+            Genesis::Population(population),
+            Parameters::default(),
+        );
+        assert_eq!(b.size(), 16);
+        assert_eq!(b.population(), 128);
+
+        let mut exported = vec![];
+        b.export(&mut exported).unwrap();
+        let exported = String::from_utf8(exported).unwrap();
+        let original = String::from_utf8(buffer).unwrap();
+        let mut lines = exported.lines().skip(2);
+        for genome in original.lines().skip(2).cycle().take(128) {
+            assert_eq!(lines.next(), Some(genome));
+        }
+    }
+}
@@ -0,0 +1,191 @@
+/// How offspring genomes deviate from the crossover of their parents
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Mutation {
+    /// Chance of an offspring having one of its genes hit by a point mutation
+    pub rate: f32,
+    /// Chance of each gene being hit by a point mutation, on top of `rate`
+    pub gene_rate: f32,
+    /// Upper bound of point mutations in a single offspring
+    pub limit: Option<usize>,
+    /// Relative likelihood of each kind of point mutation
+    pub kinds: Kinds,
+    /// Standard deviation of a nudge, in synapse weight units
+    pub sigma: f32,
+    /// Chance of an offspring having one of its genes duplicated
+    pub duplication: f32,
+    /// Chance of an offspring having one of its genes deleted
+    pub deletion: f32,
+    /// Chance of each body trait of an offspring being nudged
+    pub traits: f32,
+    /// Chance of each hidden neuron of an offspring switching to a random activation function
+    pub activations: f32,
+    /// Chance of each neuron bias of an offspring being nudged
+    pub biases: f32,
+}
+
+impl Default for Mutation {
+    fn default() -> Self {
+        Self {
+            rate: 0.001,
+            gene_rate: 0.,
+            limit: None,
+            kinds: Kinds::default(),
+            sigma: 0.5,
+            duplication: 0.,
+            deletion: 0.,
+            traits: 0.,
+            activations: 0.,
+            biases: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Kinds {
+    /// Flip a single bit anywhere in the gene
+    pub flip: f32,
+    /// Gaussian perturbation of the synapse weight, keeping the wiring
+    pub nudge: f32,
+    /// Reconnect input and output, keeping the synapse weight
+    pub rewire: f32,
+}
+
+impl Default for Kinds {
+    fn default() -> Self {
+        Self {
+            flip: 1.,
+            nudge: 0.,
+            rewire: 0.,
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub enum Kind {
+    Flip,
+    Nudge,
+    Rewire,
+}
+
+/// Count of mutations applied
+#[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
+pub struct Mutations {
+    pub flips: usize,
+    pub nudges: usize,
+    pub rewires: usize,
+    pub duplications: usize,
+    pub deletions: usize,
+    pub traits: usize,
+    pub activations: usize,
+    pub biases: usize,
+}
+
+impl Mutation {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        for (name, chance) in [
+            ("rate", self.rate),
+            ("gene_rate", self.gene_rate),
+            ("duplication", self.duplication),
+            ("deletion", self.deletion),
+            ("traits", self.traits),
+            ("activations", self.activations),
+            ("biases", self.biases),
+        ] {
+            if !(0.0..=1.0).contains(&chance) {
+                anyhow::bail!("`{name}` must be between 0 and 1, got {chance}");
+            }
+        }
+
+        let Kinds {
+            flip,
+            nudge,
+            rewire,
+        } = self.kinds;
+        if [flip, nudge, rewire]
+            .iter()
+            .any(|w| !w.is_finite() || *w < 0.)
+        {
+            anyhow::bail!("`kinds` weights must not be negative");
+        }
+        if (self.rate > 0. || self.gene_rate > 0.) && flip + nudge + rewire <= 0. {
+            anyhow::bail!("At least one of `kinds` must have a positive weight");
+        }
+
+        if !self.sigma.is_finite() || self.sigma < 0. {
+            anyhow::bail!("`sigma` must not be negative, got {}", self.sigma);
+        }
+
+        Ok(())
+    }
+
+    /// Picks which kind of point mutation to apply
+    pub fn kind(&self, rng: &mut impl rand::Rng) -> Kind {
+        let Kinds {
+            flip,
+            nudge,
+            rewire,
+        } = self.kinds;
+
+        let pick = rng.gen::<f32>() * (flip + nudge + rewire);
+        if pick < flip {
+            Kind::Flip
+        } else if pick < flip + nudge {
+            Kind::Nudge
+        } else {
+            Kind::Rewire
+        }
+    }
+}
+
+impl Mutations {
+    pub fn count(&mut self, kind: Kind) {
+        match kind {
+            Kind::Flip => self.flips += 1,
+            Kind::Nudge => self.nudges += 1,
+            Kind::Rewire => self.rewires += 1,
+        }
+    }
+
+    pub fn total(self) -> usize {
+        self.flips
+            + self.nudges
+            + self.rewires
+            + self.duplications
+            + self.deletions
+            + self.traits
+            + self.activations
+            + self.biases
+    }
+}
+
+impl std::ops::AddAssign for Mutations {
+    fn add_assign(&mut self, rhs: Self) {
+        self.flips += rhs.flips;
+        self.nudges += rhs.nudges;
+        self.rewires += rhs.rewires;
+        self.duplications += rhs.duplications;
+        self.deletions += rhs.deletions;
+        self.traits += rhs.traits;
+        self.activations += rhs.activations;
+        self.biases += rhs.biases;
+    }
+}
+
+impl std::fmt::Display for Mutations {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "flip {} nudge {} rewire {} dup {} del {} traits {} act {} bias {}",
+            self.flips,
+            self.nudges,
+            self.rewires,
+            self.duplications,
+            self.deletions,
+            self.traits,
+            self.activations,
+            self.biases
+        )
+    }
+}
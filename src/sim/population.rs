@@ -0,0 +1,130 @@
+//! Plain text format for the gene pool of a simulation
+//!
+//! The first line is the [`HEADER`], followed by a `hidden <count>` line with the number of
+//! hidden neurons the genes were encoded for. Every following line is one genome, written as
+//! space separated 8-digit hex gene words, then `|` and the body traits as a 14-digit hex word,
+//! then `|` and the neurons as a hex word of 2 digits per gene, up to 42. Blank lines and lines
+//! starting with `#` are ignored.
+
+use super::boop::{Genome, MAX_HIDDEN_NEURONS};
+
+pub const HEADER: &str = "geny population v1";
+
+pub struct Population {
+    hidden_neurons: u8,
+    genomes: Vec<Genome>,
+}
+
+impl Population {
+    pub fn read(reader: impl std::io::BufRead) -> anyhow::Result<Self> {
+        let mut lines = reader
+            .lines()
+            .enumerate()
+            .map(|(i, line)| line.map(|l| (i + 1, l)))
+            .filter(|line| {
+                line.as_ref()
+                    .map_or(true, |(_, l)| !l.trim().is_empty() && !l.starts_with('#'))
+            });
+
+        let Some((_, header)) = lines.next().transpose()? else {
+            anyhow::bail!("Population file is empty");
+        };
+        if header.trim() != HEADER {
+            anyhow::bail!("Not a population file, expected `{HEADER}` as the first line");
+        }
+
+        let Some((line, hidden)) = lines.next().transpose()? else {
+            anyhow::bail!("Missing hidden neuron count");
+        };
+        let hidden_neurons = hidden
+            .trim()
+            .strip_prefix("hidden ")
+            .ok_or_else(|| anyhow::anyhow!("Line {line}: expected `hidden <count>`"))?
+            .trim()
+            .parse()
+            .map_err(|e| anyhow::anyhow!("Line {line}: invalid hidden neuron count: {e}"))?;
+        if hidden_neurons > MAX_HIDDEN_NEURONS {
+            anyhow::bail!(
+                "Line {line}: at most {MAX_HIDDEN_NEURONS} hidden neurons are supported, got {hidden_neurons}"
+            );
+        }
+
+        let genomes = lines
+            .map(|line| {
+                let (line, text) = line?;
+                Genome::parse(&text, hidden_neurons)
+                    .map_err(|e| anyhow::anyhow!("Line {line}: {e}"))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if genomes.is_empty() {
+            anyhow::bail!("Population file has no genomes");
+        }
+
+        Ok(Self {
+            hidden_neurons,
+            genomes,
+        })
+    }
+
+    pub fn write<'a>(
+        writer: &mut impl std::io::Write,
+        hidden_neurons: u8,
+        genomes: impl Iterator<Item = &'a Genome>,
+    ) -> std::io::Result<()> {
+        writeln!(writer, "{HEADER}")?;
+        writeln!(writer, "hidden {hidden_neurons}")?;
+        for genome in genomes {
+            writeln!(writer, "{genome}")?;
+        }
+        Ok(())
+    }
+
+    #[inline]
+    pub fn hidden_neurons(&self) -> u8 {
+        self.hidden_neurons
+    }
+
+    #[inline]
+    pub fn genomes(&self) -> &[Genome] {
+        &self.genomes
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Population;
+
+    /// Traits and neurons of a genome line, neither of which matter to the file format
+    const TAIL: &str = "| 80808080808080 | 000000000000000080808080808080808080808080";
+
+    #[test]
+    fn reject_invalid_population() {
+        let read = |text: &str| Population::read(text.as_bytes());
+        let genome = format!("01 ff {TAIL}");
+        assert!(read("").is_err());
+        assert!(read("geny population v1\nhidden 4\n").is_err());
+        assert!(read("geny population v1\nhidden 4\nzz\n").is_err());
+        assert!(read("geny population v1\nhidden 4\n01 ff\n").is_err());
+        assert!(read(&format!("geny population v0\nhidden 4\n{genome}\n")).is_err());
+        assert!(read(&format!("geny population v1\nhidden 4\n# c\n{genome}\n")).is_ok());
+    }
+
+    #[test]
+    fn round_trip() {
+        let text = format!(
+            "geny population v1\nhidden 4\n0000002a 00ff00ff {TAIL}\n\n# comment\n01 {TAIL}\n"
+        );
+        let population = Population::read(text.as_bytes()).unwrap();
+        assert_eq!(population.hidden_neurons(), 4);
+        assert_eq!(population.genomes().len(), 2);
+
+        let mut written = vec![];
+        Population::write(&mut written, 4, population.genomes().iter()).unwrap();
+        let reread = Population::read(written.as_slice()).unwrap();
+
+        let mut rewritten = vec![];
+        Population::write(&mut rewritten, 4, reread.genomes().iter()).unwrap();
+        assert_eq!(written, rewritten);
+    }
+}
@@ -0,0 +1,266 @@
+use super::selection::Fitness;
+use super::world::World;
+use super::Index;
+
+/// How the survivors of a generation produce the next one
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Reproduction {
+    pub mating: Mating,
+    /// Survivors whose genomes are copied into the next generation unchanged
+    pub elitism: Option<Elitism>,
+    /// Allow a survivor to mate with itself
+    ///
+    /// When a single boop survives, it always mates with itself
+    pub selfing: bool,
+}
+
+impl Default for Reproduction {
+    fn default() -> Self {
+        Self {
+            mating: Mating::default(),
+            elitism: None,
+            selfing: true,
+        }
+    }
+}
+
+/// How each parent is picked from the survivors
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Mating {
+    /// Every survivor is equally likely
+    #[default]
+    Uniform,
+    /// Likelihood is proportional to fitness
+    Roulette(Fitness),
+    /// The fittest of `size` survivors picked at random
+    Tournament { size: usize, fitness: Fitness },
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Elitism {
+    pub count: usize,
+    pub fitness: Fitness,
+}
+
+impl Reproduction {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if let Mating::Tournament { size: 0, .. } = self.mating {
+            anyhow::bail!("Tournament size must be at least 1");
+        }
+
+        if let Some(Elitism { count: 0, .. }) = self.elitism {
+            anyhow::bail!("Elitism count must be at least 1");
+        }
+
+        Ok(())
+    }
+
+    /// The fittest survivors, best first
+    pub fn elite(&self, world: &World, survivors: &[usize]) -> Vec<usize> {
+        let Some(elitism) = &self.elitism else {
+            return vec![];
+        };
+
+        let mut ranked = score(survivors, world, elitism.fitness);
+        ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        ranked.truncate(elitism.count);
+        ranked.into_iter().map(|(i, _)| i).collect()
+    }
+
+    pub fn parents<'a>(&'a self, world: &World, survivors: &'a [usize]) -> Parents<'a> {
+        let scores = match self.mating {
+            Mating::Uniform => vec![],
+            Mating::Roulette(fitness) | Mating::Tournament { fitness, .. } => {
+                score(survivors, world, fitness)
+                    .into_iter()
+                    .map(|(_, s)| s)
+                    .collect()
+            }
+        };
+
+        let roulette = if let Mating::Roulette(_) = self.mating {
+            roulette(&scores)
+        } else {
+            None
+        };
+
+        Parents {
+            reproduction: self,
+            survivors,
+            scores,
+            roulette,
+        }
+    }
+}
+
+pub struct Parents<'a> {
+    reproduction: &'a Reproduction,
+    survivors: &'a [usize],
+    scores: Vec<f32>,
+    roulette: Option<rand::distributions::WeightedIndex<f32>>,
+}
+
+impl Parents<'_> {
+    pub fn pick(&self, rng: &mut impl rand::Rng) -> (usize, usize) {
+        let father = self.choose(rng);
+        let mut mother = self.choose(rng);
+
+        let count = self.survivors.len();
+        if !self.reproduction.selfing && count > 1 {
+            // Fitness driven picks may keep landing on the same survivor, so only retry so much
+            for _ in 0..count {
+                if mother != father {
+                    break;
+                }
+                mother = self.choose(rng);
+            }
+
+            if mother == father {
+                mother = (father + rng.gen_range(1..count)) % count;
+            }
+        }
+
+        (self.survivors[father], self.survivors[mother])
+    }
+
+    /// Position of a parent in `survivors`
+    fn choose(&self, rng: &mut impl rand::Rng) -> usize {
+        use rand::distributions::Distribution;
+
+        match self.reproduction.mating {
+            Mating::Uniform => rng.gen_range(0..self.survivors.len()),
+            Mating::Roulette(_) => {
+                if let Some(roulette) = &self.roulette {
+                    roulette.sample(rng)
+                } else {
+                    // Only when a score is not finite, see `roulette`
+                    rng.gen_range(0..self.survivors.len())
+                }
+            }
+            Mating::Tournament { size, .. } => (0..size)
+                .map(|_| rng.gen_range(0..self.survivors.len()))
+                .max_by(|a, b| self.scores[*a].total_cmp(&self.scores[*b]))
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Weights proportional to `scores`, or `None` if one of them is not finite
+///
+/// Fitness may be negative, so weights are relative to the least fit survivor. They are shifted by
+/// the spread of the scores shared among the survivors, so that the least fit one keeps a chance
+/// and equally fit ones are equally likely
+fn roulette(scores: &[f32]) -> Option<rand::distributions::WeightedIndex<f32>> {
+    let floor = scores.iter().copied().fold(f32::INFINITY, f32::min);
+    let ceiling = scores.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+
+    // ALLOWED: Precision only matters for how much the least fit survivor is favoured
+    #[allow(clippy::cast_precision_loss)]
+    let margin = (ceiling - floor).max(1.) / scores.len() as f32;
+
+    rand::distributions::WeightedIndex::new(scores.iter().map(|s| s - floor + margin)).ok()
+}
+
+fn score(survivors: &[usize], world: &World, fitness: Fitness) -> Vec<(usize, f32)> {
+    survivors
+        .iter()
+        .map(|i| (*i, fitness.score(world, Index(*i))))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Elitism, Mating, Reproduction};
+    use crate::sim::selection::Fitness;
+    use crate::sim::world::World;
+
+    #[test]
+    fn no_selfing() {
+        use rand::SeedableRng;
+
+        let world = World::fixture(4);
+        let survivors = [1, 4, 7];
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        for mating in [
+            Mating::Uniform,
+            Mating::Roulette(Fitness::Proximity),
+            Mating::Tournament {
+                size: 10,
+                fitness: Fitness::Proximity,
+            },
+        ] {
+            let reproduction = Reproduction {
+                mating,
+                elitism: None,
+                selfing: false,
+            };
+            let parents = reproduction.parents(&world, &survivors);
+            for _ in 0..32 {
+                let (father, mother) = parents.pick(&mut rng);
+                assert_ne!(father, mother);
+                assert!(survivors.contains(&father));
+                assert!(survivors.contains(&mother));
+            }
+        }
+    }
+
+    #[test]
+    fn roulette() {
+        use rand::distributions::Distribution;
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        // The least fit survivor keeps a chance
+        let uneven = super::roulette(&[-3., 5., 1.]).unwrap();
+        let mut picked = [0; 3];
+        for _ in 0..1024 {
+            picked[uneven.sample(&mut rng)] += 1;
+        }
+        assert!(picked.iter().all(|count| *count > 0), "{picked:?}");
+        assert!(picked[1] > picked[2] && picked[2] > picked[0], "{picked:?}");
+
+        // Equally fit survivors do not fall back to a uniform pick
+        assert!(super::roulette(&[2., 2.]).is_some());
+        assert!(super::roulette(&[0.]).is_some());
+
+        // Boops far from food, or without any, still get a finite weight
+        let barren = World::fixture(0);
+        let survivors = [1, 4, 7];
+        let reproduction = Reproduction {
+            mating: Mating::Roulette(Fitness::Proximity),
+            ..Reproduction::default()
+        };
+        assert!(reproduction.parents(&barren, &survivors).roulette.is_some());
+        assert!(reproduction
+            .parents(&World::fixture(4), &survivors)
+            .roulette
+            .is_some());
+    }
+
+    #[test]
+    fn elite() {
+        let world = World::fixture(4);
+        let survivors = (0..10).collect::<Vec<_>>();
+        let reproduction = Reproduction {
+            elitism: Some(Elitism {
+                count: 3,
+                fitness: Fitness::Proximity,
+            }),
+            ..Reproduction::default()
+        };
+
+        let elite = reproduction.elite(&world, &survivors);
+        assert_eq!(elite.len(), 3);
+
+        let score = |i| Fitness::Proximity.score(&world, crate::sim::Index(i));
+        assert!(score(elite[0]) >= score(elite[1]));
+        assert!(score(elite[1]) >= score(elite[2]));
+        for other in survivors.iter().filter(|i| !elite.contains(i)) {
+            assert!(score(*other) <= score(elite[2]));
+        }
+    }
+}
@@ -0,0 +1,207 @@
+use super::world::World;
+use super::Index;
+
+/// Rule deciding which boops survive to the next generation
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+#[serde(rename_all = "snake_case", deny_unknown_fields)]
+pub enum Selection {
+    /// Within reach of food at the end of the day
+    #[default]
+    OnFood,
+    /// Inside the rectangle at the end of the day
+    Region {
+        x: f32,
+        y: f32,
+        width: f32,
+        height: f32,
+    },
+    /// Moved at least this far during the day
+    Travelled(f32),
+    /// Spent at least this many days within reach of food, or ate this much consumable food
+    Eaten(usize),
+    /// Has at least this much energy left at the end of the day
+    Energy(f32),
+    /// The best `count` boops by `fitness`
+    Top { count: usize, fitness: Fitness },
+    /// Each rule picks among the survivors of the previous one
+    All(Vec<Selection>),
+    /// Survives any of the rules
+    Any(Vec<Selection>),
+}
+
+/// Score of how well a boop did during the day, higher is better
+#[derive(Debug, Copy, Clone, serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Fitness {
+    Travelled,
+    Eaten,
+    /// Closeness to the nearest food at the end of the day
+    Proximity,
+    /// Energy left at the end of the day
+    Energy,
+}
+
+impl Selection {
+    /// Whether any part of the rule keeps boops for being within reach of food
+    pub fn on_food(&self) -> bool {
+        match self {
+            Self::OnFood => true,
+            Self::All(selections) | Self::Any(selections) => selections.iter().any(Self::on_food),
+            _ => false,
+        }
+    }
+
+    pub fn validate(&self) -> anyhow::Result<()> {
+        match self {
+            Self::OnFood | Self::Eaten(_) => Ok(()),
+            Self::Region { width, height, .. } => {
+                if *width > 0. && *height > 0. {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Selection region must have a positive size")
+                }
+            }
+            Self::Travelled(distance) => {
+                if distance.is_finite() {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Selection travelled distance must be finite")
+                }
+            }
+            Self::Energy(energy) => {
+                if energy.is_finite() {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Selection energy must be finite")
+                }
+            }
+            Self::Top { count, .. } => {
+                if *count > 0 {
+                    Ok(())
+                } else {
+                    anyhow::bail!("Selection top count must be at least 1")
+                }
+            }
+            Self::All(selections) | Self::Any(selections) => {
+                if selections.is_empty() {
+                    anyhow::bail!("Selection composition must not be empty")
+                }
+                selections.iter().try_for_each(Self::validate)
+            }
+        }
+    }
+
+    /// Narrows `candidates` down to the ones that survive, keeping their order
+    pub fn select(&self, world: &World, candidates: Vec<usize>) -> Vec<usize> {
+        match self {
+            Self::OnFood => filter(candidates, |i| world.on_food(i)),
+            Self::Region {
+                x,
+                y,
+                width,
+                height,
+            } => filter(candidates, |i| {
+                let coord = world.boop(i);
+                (*x..=x + width).contains(&coord.x()) && (*y..=y + height).contains(&coord.y())
+            }),
+            Self::Travelled(distance) => {
+                filter(candidates, |i| world.record(i).travelled >= *distance)
+            }
+            Self::Eaten(meals) => filter(candidates, |i| world.record(i).eaten >= *meals),
+            Self::Energy(energy) => filter(candidates, |i| world.energy(i) >= *energy),
+            Self::Top { count, fitness } => {
+                let mut ranked = candidates
+                    .into_iter()
+                    .map(|i| (i, fitness.score(world, Index(i))))
+                    .collect::<Vec<_>>();
+                ranked.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+                ranked.truncate(*count);
+
+                let mut survivors = ranked.into_iter().map(|(i, _)| i).collect::<Vec<_>>();
+                survivors.sort_unstable();
+                survivors
+            }
+            Self::All(selections) => selections.iter().fold(candidates, |candidates, selection| {
+                selection.select(world, candidates)
+            }),
+            Self::Any(selections) => {
+                let mut survivors = selections
+                    .iter()
+                    .flat_map(|selection| selection.select(world, candidates.clone()))
+                    .collect::<Vec<_>>();
+                survivors.sort_unstable();
+                survivors.dedup();
+                survivors
+            }
+        }
+    }
+}
+
+impl Fitness {
+    pub fn score(self, world: &World, index: Index) -> f32 {
+        match self {
+            Self::Travelled => world.record(index).travelled,
+            // ALLOWED: Precision only matters for ranking, which is preserved
+            #[allow(clippy::cast_precision_loss)]
+            Self::Eaten => world.record(index).eaten as f32,
+            // Without food, a boop is further than any food of the world could be
+            Self::Proximity => world
+                .food_distance(index)
+                .map_or(-f32::from(world.size()) * std::f32::consts::SQRT_2, |d| -d),
+            Self::Energy => world.energy(index),
+        }
+    }
+}
+
+fn filter(candidates: Vec<usize>, predicate: impl Fn(Index) -> bool) -> Vec<usize> {
+    candidates
+        .into_iter()
+        .filter(|i| predicate(Index(*i)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Fitness, Selection};
+    use crate::sim::world::World;
+
+    #[test]
+    fn region() {
+        let world = World::fixture(4);
+        let everywhere = Selection::Region {
+            x: 0.,
+            y: 0.,
+            width: 16.,
+            height: 16.,
+        };
+        assert_eq!(everywhere.select(&world, (0..10).collect()).len(), 10);
+
+        let nowhere = Selection::Region {
+            x: 20.,
+            y: 20.,
+            width: 1.,
+            height: 1.,
+        };
+        assert!(nowhere.select(&world, (0..10).collect()).is_empty());
+    }
+
+    #[test]
+    fn composition() {
+        let world = World::fixture(4);
+        let top = |count| Selection::Top {
+            count,
+            fitness: Fitness::Proximity,
+        };
+
+        let survivors = top(3).select(&world, (0..10).collect());
+        assert_eq!(survivors.len(), 3);
+        assert!(survivors.is_sorted());
+
+        let narrowed = Selection::All(vec![top(3), top(1)]).select(&world, (0..10).collect());
+        assert_eq!(narrowed.len(), 1);
+        assert!(survivors.contains(&narrowed[0]));
+
+        let widened = Selection::Any(vec![top(1), top(3)]).select(&world, (0..10).collect());
+        assert_eq!(widened, survivors);
+    }
+}
@@ -0,0 +1,94 @@
+//! Binary snapshot format for persisting a [`Simulation`](super::Simulation)
+//!
+//! All values are little-endian. The stream starts with [`MAGIC`] followed by the format
+//! [`VERSION`], so that incompatible snapshots are rejected instead of misread.
+
+pub const MAGIC: &[u8; 4] = b"GENY";
+pub const VERSION: u16 = 1;
+
+pub trait Writer: std::io::Write {
+    fn write_u8(&mut self, value: u8) -> std::io::Result<()> {
+        self.write_all(&[value])
+    }
+
+    fn write_u16(&mut self, value: u16) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_u32(&mut self, value: u32) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_u64(&mut self, value: u64) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+
+    fn write_usize(&mut self, value: usize) -> std::io::Result<()> {
+        self.write_u64(value as u64)
+    }
+
+    fn write_f32(&mut self, value: f32) -> std::io::Result<()> {
+        self.write_all(&value.to_le_bytes())
+    }
+}
+
+impl<W: std::io::Write> Writer for W {}
+
+pub trait Reader: std::io::Read {
+    fn read_u8(&mut self) -> std::io::Result<u8> {
+        let mut buffer = [0; 1];
+        self.read_exact(&mut buffer)?;
+        Ok(buffer[0])
+    }
+
+    fn read_u16(&mut self) -> std::io::Result<u16> {
+        let mut buffer = [0; 2];
+        self.read_exact(&mut buffer)?;
+        Ok(u16::from_le_bytes(buffer))
+    }
+
+    fn read_u32(&mut self) -> std::io::Result<u32> {
+        let mut buffer = [0; 4];
+        self.read_exact(&mut buffer)?;
+        Ok(u32::from_le_bytes(buffer))
+    }
+
+    fn read_u64(&mut self) -> std::io::Result<u64> {
+        let mut buffer = [0; 8];
+        self.read_exact(&mut buffer)?;
+        Ok(u64::from_le_bytes(buffer))
+    }
+
+    fn read_usize(&mut self) -> anyhow::Result<usize> {
+        Ok(usize::try_from(self.read_u64()?)?)
+    }
+
+    fn read_f32(&mut self) -> std::io::Result<f32> {
+        let mut buffer = [0; 4];
+        self.read_exact(&mut buffer)?;
+        Ok(f32::from_le_bytes(buffer))
+    }
+}
+
+impl<R: std::io::Read> Reader for R {}
+
+pub fn write_header(writer: &mut impl Writer) -> std::io::Result<()> {
+    writer.write_all(MAGIC)?;
+    writer.write_u16(VERSION)
+}
+
+/// Checks the stream is a snapshot written in the current format version
+pub fn read_header(reader: &mut impl Reader) -> anyhow::Result<()> {
+    let mut magic = [0; 4];
+    reader.read_exact(&mut magic)?;
+    if &magic != MAGIC {
+        anyhow::bail!("Not a simulation snapshot");
+    }
+
+    let version = reader.read_u16()?;
+    if version != VERSION {
+        anyhow::bail!("Unsupported snapshot version {version}, expected {VERSION}");
+    }
+
+    Ok(())
+}
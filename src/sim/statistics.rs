@@ -0,0 +1,186 @@
+use super::Simulation;
+
+/// Summary of a generation, taken right after it was spawned
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Statistics {
+    pub generation: usize,
+    pub population: usize,
+    /// Survivors of the previous generation
+    pub survivors: usize,
+    /// Share of the previous generation that survived
+    pub survival_rate: f32,
+    /// Number of distinct genome signatures
+    pub signatures: usize,
+    /// Most common genome signature
+    pub dominant: u32,
+    /// Share of the population carrying the most common signature
+    pub dominant_share: f32,
+    /// Average number of genes in a genome
+    pub genome_length: f32,
+    pub weight_mean: f32,
+    pub weight_variance: f32,
+    /// Number of genes building a direct axon, from input to output
+    pub direct: usize,
+    /// Number of genes building an axon from an input into a hidden neuron
+    pub into_hidden: usize,
+    /// Number of genes building an axon between hidden neurons
+    pub inter_hidden: usize,
+    /// Number of genes building an axon from a hidden neuron to an output
+    pub from_hidden: usize,
+    /// Average body traits, as expressed factors
+    pub speed: f32,
+    pub turn_rate: f32,
+    pub size: f32,
+    pub sensing: f32,
+}
+
+impl Statistics {
+    // ALLOWED: Counts are far below the point where precision matters for a summary
+    #[allow(clippy::cast_precision_loss, clippy::cast_possible_truncation)]
+    pub fn collect(simulation: &Simulation) -> Self {
+        let population = simulation.population();
+
+        let mut signatures = std::collections::HashMap::<u32, usize>::new();
+        let mut genes = 0;
+        let mut kinds = [0; 4];
+        let (mut sum, mut squares) = (0_f64, 0_f64);
+        let mut traits = [0_f32; 4];
+
+        for boop in simulation.boops() {
+            let genome = boop.genome();
+            *signatures.entry(genome.signature()).or_default() += 1;
+
+            genes += genome.length();
+            for (total, count) in kinds.iter_mut().zip(genome.axon_kinds()) {
+                *total += count;
+            }
+            for weight in genome.weights() {
+                sum += f64::from(weight);
+                squares += f64::from(weight) * f64::from(weight);
+            }
+
+            let expressed = genome.traits();
+            for (total, factor) in traits.iter_mut().zip([
+                expressed.speed(),
+                expressed.turn_rate(),
+                expressed.size(),
+                expressed.sensing(),
+            ]) {
+                *total += factor;
+            }
+        }
+
+        // Ties are broken by the lowest signature so that the summary is deterministic
+        let (dominant, carriers) = signatures
+            .iter()
+            .map(|(signature, count)| (*signature, *count))
+            .max_by(|(a, a_count), (b, b_count)| a_count.cmp(b_count).then(b.cmp(a)))
+            .unwrap_or_default();
+
+        let ratio = |part: usize, whole: usize| {
+            if whole == 0 {
+                0.
+            } else {
+                part as f32 / whole as f32
+            }
+        };
+
+        let mean = if genes == 0 { 0. } else { sum / genes as f64 };
+        let variance = if genes == 0 {
+            0.
+        } else {
+            (squares / genes as f64 - mean * mean).max(0.)
+        };
+
+        let [direct, into_hidden, inter_hidden, from_hidden] = kinds;
+        let [speed, turn_rate, size, sensing] = traits.map(|total| {
+            if population == 0 {
+                0.
+            } else {
+                total / population as f32
+            }
+        });
+
+        Self {
+            generation: simulation.generation(),
+            population,
+            survivors: simulation.survivors(),
+            survival_rate: ratio(simulation.survivors(), population),
+            signatures: signatures.len(),
+            dominant,
+            dominant_share: ratio(carriers, population),
+            genome_length: ratio(genes, population),
+            weight_mean: mean as f32,
+            weight_variance: variance as f32,
+            direct,
+            into_hidden,
+            inter_hidden,
+            from_hidden,
+            speed,
+            turn_rate,
+            size,
+            sensing,
+        }
+    }
+
+    /// Summary of the generation that would have followed the current one, had anyone survived
+    pub fn extinct(simulation: &Simulation) -> Self {
+        Self {
+            generation: simulation.generation() + 1,
+            ..Self::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::Statistics;
+    use crate::sim::{Genesis, Parameters, Population, Simulation};
+
+    #[test]
+    fn census() {
+        let tail = "| 80808080808080 | 000000000000000080808080808080808080808080";
+        let text = format!(
+            "geny population v1\nhidden 4\n00000000 00100000 {tail}\n00000000 00100000 {tail}\n\
+             00000000 {tail}\n"
+        );
+        let population = Population::read(text.as_bytes()).unwrap();
+        let simulation = Simulation::new(
+            27,
+            8,
+            3,
+            Genesis::Population(population),
+            Parameters::default(),
+        );
+
+        let statistics = Statistics::collect(&simulation);
+        assert_eq!(statistics.population, 3);
+        assert_eq!(statistics.signatures, 2);
+        assert!((statistics.dominant_share - 2. / 3.).abs() < f32::EPSILON);
+        assert!((statistics.genome_length - 5. / 3.).abs() < f32::EPSILON);
+        assert_eq!(statistics.direct, 5);
+        assert_eq!(statistics.into_hidden + statistics.inter_hidden, 0);
+        assert!((statistics.weight_mean + 4.).abs() < f32::EPSILON);
+        assert!(statistics.weight_variance.abs() < f32::EPSILON);
+        assert!((statistics.speed - 1.).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn extinct() {
+        let simulation = Simulation::new(
+            27,
+            8,
+            3,
+            Genesis::Random {
+                synapses: 4,
+                hidden_neurons: 2,
+            },
+            Parameters::default(),
+        );
+
+        let statistics = Statistics::extinct(&simulation);
+        assert_eq!(statistics.generation, simulation.generation() + 1);
+        assert_eq!(statistics.population, 0);
+        assert_eq!(statistics.survivors, 0);
+    }
+}
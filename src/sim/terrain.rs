@@ -0,0 +1,358 @@
+use super::snapshot::{Reader, Writer};
+use super::world::{Coordinate, Direction};
+
+/// Random coordinates tried by [`Terrain::spot`] before it picks among the open tiles instead
+const TRIES: usize = 64;
+
+/// What occupies a unit square of the world
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Tile {
+    Open,
+    /// Nothing moves into it
+    Wall,
+    /// Hurts whoever stands in it, see [`Hazard`]
+    Lava,
+}
+
+/// What lava does to a boop standing in it
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Hazard {
+    /// Health lost for every day spent in lava, a boop starts with 1 and dies at 0
+    pub damage: f32,
+}
+
+impl Default for Hazard {
+    fn default() -> Self {
+        Self { damage: 1. }
+    }
+}
+
+impl Hazard {
+    pub fn validate(&self) -> anyhow::Result<()> {
+        if self.damage.is_finite() && self.damage >= 0. {
+            Ok(())
+        } else {
+            anyhow::bail!("Lava damage must not be negative, got {}", self.damage)
+        }
+    }
+}
+
+/// Static layout of the world, kept from one generation to the next
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terrain {
+    size: u8,
+    tiles: Vec<Tile>,
+}
+
+/// How the terrain of a new world is laid out
+#[derive(Debug, Clone, Default)]
+pub enum Landscape {
+    /// No obstacles at all
+    #[default]
+    Open,
+    /// A hand drawn map
+    Map(Terrain),
+    /// Random rectangles of walls and lava covering about `density` and `lava` of the world
+    Scattered { density: f32, lava: f32 },
+}
+
+impl Landscape {
+    pub fn validate(&self, size: u8) -> anyhow::Result<()> {
+        match self {
+            Self::Open => Ok(()),
+            Self::Map(terrain) => {
+                if terrain.size == size {
+                    Ok(())
+                } else {
+                    anyhow::bail!(
+                        "Map is {0}x{0} but the world is {size}x{size}",
+                        terrain.size
+                    )
+                }
+            }
+            Self::Scattered { density, lava } => {
+                if *density >= 0. && *lava >= 0. && density + lava <= 0.9 {
+                    Ok(())
+                } else {
+                    anyhow::bail!(
+                        "Wall and lava densities must not be negative and add up to at most 0.9, \
+                         got {density} and {lava}"
+                    )
+                }
+            }
+        }
+    }
+
+    pub fn build(&self, size: u8, rng: &mut impl rand::Rng) -> Terrain {
+        match self {
+            Self::Open => Terrain::open(size),
+            Self::Map(terrain) => terrain.clone(),
+            Self::Scattered { density, lava } => {
+                let mut terrain = Terrain::open(size);
+                terrain.scatter(Tile::Wall, *density, rng);
+                terrain.scatter(Tile::Lava, *lava, rng);
+                terrain
+            }
+        }
+    }
+}
+
+impl Terrain {
+    pub fn open(size: u8) -> Self {
+        Self {
+            size,
+            tiles: vec![Tile::Open; usize::from(size) * usize::from(size)],
+        }
+    }
+
+    /// Parses a square map with one line per row, `#` for walls, `~` for lava and `.` for open
+    /// ground
+    pub fn parse(text: &str) -> anyhow::Result<Self> {
+        let rows = text
+            .lines()
+            .map(str::trim_end)
+            .filter(|line| !line.is_empty())
+            .collect::<Vec<_>>();
+
+        let Ok(size) = u8::try_from(rows.len()) else {
+            anyhow::bail!("Map has {} rows, at most 255 are supported", rows.len());
+        };
+        if size < 2 {
+            anyhow::bail!("Map must be at least 2x2");
+        }
+
+        let mut tiles = Vec::with_capacity(rows.len() * rows.len());
+        for (y, row) in rows.iter().enumerate() {
+            if row.chars().count() != rows.len() {
+                anyhow::bail!(
+                    "Row {} of the map is {} wide, the map must be square",
+                    y + 1,
+                    row.chars().count()
+                );
+            }
+            for (x, c) in row.chars().enumerate() {
+                tiles.push(match c {
+                    '.' => Tile::Open,
+                    '#' => Tile::Wall,
+                    '~' => Tile::Lava,
+                    _ => anyhow::bail!("Unknown tile `{c}` at column {} of row {}", x + 1, y + 1),
+                });
+            }
+        }
+
+        if !tiles.contains(&Tile::Open) {
+            anyhow::bail!("Map has no open ground to spawn on");
+        }
+
+        Ok(Self { size, tiles })
+    }
+
+    /// Turns random rectangles of open ground into `kind` until about `density` of the world
+    /// is covered
+    fn scatter(&mut self, kind: Tile, density: f32, rng: &mut impl rand::Rng) {
+        let size = self.size;
+
+        // ALLOWED: Density is at most 0.9 and there are at most 255² tiles
+        #[allow(
+            clippy::cast_precision_loss,
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss
+        )]
+        let target = (self.tiles.len() as f32 * density) as usize;
+        let longest = (size / 4).max(1);
+
+        let mut covered = 0;
+        while covered < target {
+            let width = rng.gen_range(1..=longest);
+            let height = rng.gen_range(1..=longest);
+            let left = rng.gen_range(0..=size - width);
+            let top = rng.gen_range(0..=size - height);
+
+            for y in top..top + height {
+                for x in left..left + width {
+                    let tile = self.tile_mut(x, y);
+                    if *tile == Tile::Open && covered < target {
+                        *tile = kind;
+                        covered += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    #[inline]
+    pub fn size(&self) -> u8 {
+        self.size
+    }
+
+    #[inline]
+    pub fn tile(&self, x: u8, y: u8) -> Tile {
+        self.tiles[usize::from(y) * usize::from(self.size) + usize::from(x)]
+    }
+
+    fn tile_mut(&mut self, x: u8, y: u8) -> &mut Tile {
+        &mut self.tiles[usize::from(y) * usize::from(self.size) + usize::from(x)]
+    }
+
+    /// The tile under a coordinate inside the world
+    #[inline]
+    pub fn at(&self, coord: Coordinate) -> Tile {
+        self.tile(coord.x_index(), coord.y_index())
+    }
+
+    /// Safe ground to spawn on
+    #[inline]
+    pub fn is_open(&self, coord: Coordinate) -> bool {
+        self.at(coord) == Tile::Open
+    }
+
+    /// Random coordinate on open ground
+    ///
+    /// Coordinates span `0..=size - 1`, so tiles of the last row and column are only reachable
+    /// on their edge and random coordinates never land there. After a few misses, an open tile is
+    /// picked instead, which always succeeds since every terrain has open ground
+    pub fn spot(&self, rng: &mut impl rand::Rng) -> Coordinate {
+        use rand::seq::IteratorRandom;
+
+        let max = f32::from(self.size - 1);
+        for _ in 0..TRIES {
+            let coord = Coordinate::new(rng.gen::<f32>() * max, rng.gen::<f32>() * max);
+            if self.is_open(coord) {
+                return coord;
+            }
+        }
+
+        let (x, y) = self.tiles(Tile::Open).choose(rng).unwrap_or_default();
+        // Within the first half of the tile, so that rounding never carries into the next one
+        let mut within = |tile: u8| (f32::from(tile) + rng.gen::<f32>() / 2.).min(max);
+        Coordinate::new(within(x), within(y))
+    }
+
+    /// Anything but a wall
+    #[inline]
+    pub fn is_passable(&self, coord: Coordinate) -> bool {
+        self.at(coord) != Tile::Wall
+    }
+
+    /// Every tile of `kind`, as column and row
+    pub fn tiles(&self, kind: Tile) -> impl Iterator<Item = (u8, u8)> + '_ {
+        (0..self.size)
+            .flat_map(move |y| (0..self.size).map(move |x| (x, y)))
+            .filter(move |(x, y)| self.tile(*x, *y) == kind)
+    }
+
+    /// Distance to the first wall or border straight ahead, if it is within `range`
+    pub fn wall_ahead(&self, from: Coordinate, direction: Direction, range: f32) -> Option<f32> {
+        const STEP: f32 = 0.25;
+
+        let max = f32::from(self.size - 1);
+        let (dx, dy) = (direction.as_rad().cos(), direction.as_rad().sin());
+
+        let mut distance = STEP;
+        while distance <= range {
+            let (x, y) = (from.x() + dx * distance, from.y() + dy * distance);
+            if !(0.0..=max).contains(&x)
+                || !(0.0..=max).contains(&y)
+                || !self.is_passable(Coordinate::new(x, y))
+            {
+                return Some(distance);
+            }
+            distance += STEP;
+        }
+        None
+    }
+
+    pub fn save(&self, writer: &mut impl Writer) -> std::io::Result<()> {
+        for tile in &self.tiles {
+            writer.write_u8(match tile {
+                Tile::Open => 0,
+                Tile::Wall => 1,
+                Tile::Lava => 2,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn load(reader: &mut impl Reader, size: u8) -> anyhow::Result<Self> {
+        let tiles = (0..usize::from(size) * usize::from(size))
+            .map(|_| match reader.read_u8()? {
+                0 => Ok(Tile::Open),
+                1 => Ok(Tile::Wall),
+                2 => Ok(Tile::Lava),
+                tile => anyhow::bail!("Unknown tile {tile}"),
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        if !tiles.contains(&Tile::Open) {
+            anyhow::bail!("Terrain has no open ground");
+        }
+
+        Ok(Self { size, tiles })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{Landscape, Terrain, Tile};
+    use crate::sim::world::{Coordinate, Direction};
+
+    #[test]
+    fn parse() {
+        let terrain = Terrain::parse("..~\n.#.\n...\n").unwrap();
+        assert_eq!(terrain.size(), 3);
+        assert_eq!(terrain.tile(1, 1), Tile::Wall);
+        assert_eq!(terrain.tiles(Tile::Wall).collect::<Vec<_>>(), [(1, 1)]);
+        assert_eq!(terrain.tiles(Tile::Lava).collect::<Vec<_>>(), [(2, 0)]);
+
+        assert!(Terrain::parse("...\n..\n...\n").is_err());
+        assert!(Terrain::parse("..\n.x\n").is_err());
+        assert!(Terrain::parse("##\n##\n").is_err());
+    }
+
+    #[test]
+    fn spot() {
+        use rand::SeedableRng;
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+
+        // The only open tile is in the last row and column
+        let terrain = Terrain::parse("##\n#.\n").unwrap();
+        for _ in 0..4 {
+            assert_eq!(terrain.spot(&mut rng), Coordinate::new(1., 1.));
+        }
+
+        let terrain = Terrain::parse("...#\n####\n####\n####\n").unwrap();
+        for _ in 0..16 {
+            assert!(terrain.is_open(terrain.spot(&mut rng)));
+        }
+    }
+
+    #[test]
+    fn scattered() {
+        use rand::SeedableRng;
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(27);
+        let landscape = Landscape::Scattered {
+            density: 0.25,
+            lava: 0.125,
+        };
+        let terrain = landscape.build(32, &mut rng);
+        assert_eq!(terrain.tiles(Tile::Wall).count(), 32 * 32 / 4);
+        assert_eq!(terrain.tiles(Tile::Lava).count(), 32 * 32 / 8);
+    }
+
+    #[test]
+    fn wall_ahead() {
+        let terrain = Terrain::parse("....\n...#\n....\n....\n").unwrap();
+        let from = Coordinate::new(0.5, 1.5);
+
+        let east = Direction::from_rad(0.);
+        let distance = terrain.wall_ahead(from, east, 8.).unwrap();
+        assert!((2.5..=2.75).contains(&distance));
+        assert!(terrain.wall_ahead(from, east, 2.).is_none());
+
+        // Borders count as walls
+        let north = Direction::from_rad(-std::f32::consts::FRAC_PI_2);
+        assert!(terrain.wall_ahead(from, north, 8.).unwrap() <= 1.75);
+    }
+}
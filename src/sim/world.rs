@@ -1,36 +1,106 @@
+use super::food::{self, Forage};
+use super::grid::Grid;
+use super::snapshot::{Reader, Writer};
+use super::terrain::{Terrain, Tile};
 use super::Index;
 
 pub struct World {
-    size: u8,
+    terrain: Terrain,
     sizef: f32,
     boops: Vec<Coordinate>,
+    records: Vec<Record>,
     food: Vec<Coordinate>,
-    // Walls
-    // Foods
-    // Lava
-    // TODO: Consider maybe using a grid here as a cache to represent the locations
+    /// Boops by location, as of the last [`Self::reindex`]
+    boop_grid: Grid,
+    /// Distance each boop moved since the last [`Self::reindex`]
+    moved: Vec<f32>,
+    /// Farthest any boop moved since the last [`Self::reindex`], which pads grid lookups
+    drift: f32,
+    food_grid: Grid,
+    /// Spots that food clusters around, see [`food::Spawn::Clustered`]
+    centres: Vec<Coordinate>,
+    /// Days gone by since the world was created
+    day: usize,
+    /// Remaining health of every boop, dead at zero
+    health: Vec<f32>,
+    /// Remaining energy of every boop, see [`super::Metabolism`]
+    energy: Vec<f32>,
+    /// How close every boop must get to food to eat it
+    reach: Vec<f32>,
+    /// Genome signature of every boop, for kin to recognise each other
+    signatures: Vec<u32>,
 }
 
 impl World {
-    pub fn new(size: u8, count: usize, food_count: usize) -> Self {
-        let sizef = f32::from(size - 1);
+    pub fn new(
+        terrain: Terrain,
+        count: usize,
+        food_count: usize,
+        forage: &Forage,
+        rng: &mut impl rand::Rng,
+    ) -> Self {
+        let boops = build_vec!(|| terrain.spot(rng), count);
+        let centres = forage.centres(&terrain, rng);
+        let food = build_vec!(|| forage.place(&terrain, &centres, 0, rng), food_count);
+
+        let mut world = Self::with(terrain, boops, food);
+        world.centres = centres;
+        world
+    }
+
+    pub fn with(terrain: Terrain, boops: Vec<Coordinate>, food: Vec<Coordinate>) -> Self {
+        let sizef = f32::from(terrain.size() - 1);
+
+        let mut boop_grid = Grid::new(sizef, boops.len());
+        boop_grid.rebuild(&boops);
+        let mut food_grid = Grid::new(sizef, food.len());
+        food_grid.rebuild(&food);
+
         Self {
-            size,
+            terrain,
             sizef,
-            boops: build_vec!(
-                || Coordinate::new(rand::random::<f32>() * sizef, rand::random::<f32>() * sizef,),
-                count
-            ),
-            food: build_vec!(
-                || Coordinate::new(rand::random::<f32>() * sizef, rand::random::<f32>() * sizef,),
-                food_count
-            ),
+            records: vec![Record::default(); boops.len()],
+            moved: vec![0.; boops.len()],
+            drift: 0.,
+            health: vec![1.; boops.len()],
+            energy: vec![1.; boops.len()],
+            reach: vec![1.; boops.len()],
+            signatures: vec![0; boops.len()],
+            boops,
+            food,
+            boop_grid,
+            food_grid,
+            centres: Vec::new(),
+            day: 0,
         }
     }
 
+    /// Open 16x16 world of 10 boops and `food_count` food, laid out the same way every time
+    #[cfg(test)]
+    pub fn fixture(food_count: usize) -> Self {
+        use rand::SeedableRng;
+        Self::new(
+            Terrain::open(16),
+            10,
+            food_count,
+            &Forage::default(),
+            &mut rand::rngs::StdRng::seed_from_u64(27),
+        )
+    }
+
     #[inline]
     pub fn size(&self) -> u8 {
-        self.size
+        self.terrain.size()
+    }
+
+    #[inline]
+    pub fn terrain(&self) -> &Terrain {
+        &self.terrain
+    }
+
+    #[inline]
+    pub fn population(&self) -> usize {
+        self.boops.len()
     }
 
     #[inline]
@@ -38,6 +108,55 @@ impl World {
         unsafe { *self.boops.get_unchecked(index.0) }
     }
 
+    #[inline]
+    pub fn alive(&self, index: Index) -> bool {
+        unsafe { *self.health.get_unchecked(index.0) > 0. }
+    }
+
+    #[inline]
+    pub fn energy(&self, index: Index) -> f32 {
+        unsafe { *self.energy.get_unchecked(index.0) }
+    }
+
+    /// Distance a boop moved since the last [`Self::reindex`], which is the day so far
+    #[inline]
+    pub fn moved(&self, index: Index) -> f32 {
+        unsafe { *self.moved.get_unchecked(index.0) }
+    }
+
+    /// Burns `cost` energy of a living boop, which dies once it runs dry
+    pub fn exert(&mut self, index: Index, cost: f32) {
+        if !self.alive(index) {
+            return;
+        }
+        let energy = unsafe { self.energy.get_unchecked_mut(index.0) };
+        *energy -= cost;
+        if *energy <= 0. {
+            *energy = 0.;
+            unsafe { *self.health.get_unchecked_mut(index.0) = 0. };
+        }
+    }
+
+    /// Sets how close a boop must get to food to eat it, and how others recognise it
+    pub fn embody(&mut self, index: Index, reach: f32, signature: u32) {
+        unsafe {
+            *self.reach.get_unchecked_mut(index.0) = reach;
+            *self.signatures.get_unchecked_mut(index.0) = signature;
+        }
+    }
+
+    #[inline]
+    pub fn signature(&self, index: Index) -> u32 {
+        unsafe { *self.signatures.get_unchecked(index.0) }
+    }
+
+    /// Counts a meal for a boop, restoring `energy` up to a full tank
+    fn feed(&mut self, index: Index, energy: f32) {
+        unsafe { self.records.get_unchecked_mut(index.0) }.eaten += 1;
+        let tank = unsafe { self.energy.get_unchecked_mut(index.0) };
+        *tank = (*tank + energy).min(1.);
+    }
+
     #[inline]
     fn boop_mut(&mut self, index: Index) -> &mut Coordinate {
         unsafe { self.boops.get_unchecked_mut(index.0) }
@@ -48,30 +167,281 @@ impl World {
         self.food.iter()
     }
 
-    pub fn on_food(&mut self, index: Index) -> bool {
-        for food in &self.food {
-            if food.distance(self.boop(index)) < 1. {
-                return true;
+    #[inline]
+    pub fn record(&self, index: Index) -> Record {
+        unsafe { *self.records.get_unchecked(index.0) }
+    }
+
+    pub fn on_food(&self, index: Index) -> bool {
+        let reach = unsafe { *self.reach.get_unchecked(index.0) };
+        self.food_distance(index).is_some_and(|d| d < reach)
+    }
+
+    pub fn food_distance(&self, index: Index) -> Option<f32> {
+        self.nearest_food(index).map(|(_, d)| d)
+    }
+
+    /// The closest food and its distance
+    pub fn nearest_food(&self, index: Index) -> Option<(Coordinate, f32)> {
+        self.food_grid
+            .nearest(&self.food, self.boop(index))
+            .map(|(i, d)| (self.food[i], d))
+    }
+
+    /// The other living boops within `radius`, surveyed in a single pass
+    pub fn neighbours(&self, index: Index, radius: f32) -> Neighbours {
+        let mut neighbours = Neighbours::default();
+        for (other, distance) in self.boops_around(self.boop(index), radius) {
+            if other == index {
+                continue;
+            }
+            if neighbours.nearest.is_none_or(|(_, d)| distance < d) {
+                neighbours.nearest = Some((other, distance));
             }
+            neighbours.count += 1;
         }
-        false
+        neighbours
+    }
+
+    /// Living boops within `radius` of `center`
+    pub fn boops_near(&self, center: Coordinate, radius: f32) -> impl Iterator<Item = Index> + '_ {
+        self.boops_around(center, radius).map(|(i, _)| i)
+    }
+
+    /// Living boops within `radius` of `center`, with their distance
+    fn boops_around(
+        &self,
+        center: Coordinate,
+        radius: f32,
+    ) -> impl Iterator<Item = (Index, f32)> + '_ {
+        // Boops that moved since the grid was built are still filed where they were
+        self.boop_grid
+            .candidates(center, radius + self.drift)
+            .map(move |i| (Index(i), self.boops[i].distance(center)))
+            .filter(move |(i, d)| *d <= radius && self.alive(*i))
+    }
+
+    /// Brings the location of boops up to date after they moved
+    pub fn reindex(&mut self) {
+        self.boop_grid.rebuild(&self.boops);
+        self.moved.fill(0.);
+        self.drift = 0.;
     }
 
     pub fn advance(&mut self, index: Index, speed: f32, direction: Direction) {
-        let mut coord = self.boop(index);
+        self.move_to(index, self.destination(index, speed, direction));
+    }
 
-        coord.translate(direction, speed, self.sizef);
+    /// Where the boop would end up by moving, clamped to the borders
+    ///
+    /// A boop running into a wall slides along it if it can, or else stays put. Walls are a tile
+    /// wide, so fast boops move a unit at most at a time so as not to jump over one
+    pub fn destination(&self, index: Index, speed: f32, direction: Direction) -> Coordinate {
+        // ALLOWED: Speeds are a few units at most
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let (steps, step) = {
+            let steps = speed.abs().ceil().max(1.) as usize;
+            (steps, speed / steps as f32)
+        };
+
+        let mut from = self.boop(index);
+        for _ in 0..steps {
+            let mut coord = from;
+            coord.translate(direction, step, self.sizef);
+
+            from = [
+                coord,
+                Coordinate::new(coord.x(), from.y()),
+                Coordinate::new(from.x(), coord.y()),
+            ]
+            .into_iter()
+            .find(|c| self.terrain.is_passable(*c))
+            .unwrap_or(from);
+        }
+        from
+    }
 
+    pub fn move_to(&mut self, index: Index, coord: Coordinate) {
+        let travelled = coord.distance(self.boop(index));
         *self.boop_mut(index) = coord;
+        unsafe { self.records.get_unchecked_mut(index.0) }.travelled += travelled;
+
+        let moved = unsafe { self.moved.get_unchecked_mut(index.0) };
+        *moved += travelled;
+        self.drift = self.drift.max(*moved);
+    }
+
+    /// Clamps a coordinate to the borders
+    pub fn clamp(&self, coord: Coordinate) -> Coordinate {
+        Coordinate::new(
+            coord.x().clamp(0., self.sizef),
+            coord.y().clamp(0., self.sizef),
+        )
+    }
+
+    /// Takes `damage` off the health of every living boop standing in lava
+    pub fn scorch(&mut self, damage: f32) {
+        for (boop, health) in self.boops.iter().zip(&mut self.health) {
+            if *health > 0. && self.terrain.at(*boop) == Tile::Lava {
+                *health -= damage;
+            }
+        }
+    }
+
+    /// Counts a meal for every living boop currently within reach of food
+    ///
+    /// Food that is consumed only feeds the closest boop, and is gone afterwards. Every meal
+    /// restores `meal` energy
+    pub fn graze(&mut self, forage: &Forage, meal: f32) {
+        if !forage.consumed {
+            for index in 0..self.boops.len() {
+                if self.alive(Index(index)) && self.on_food(Index(index)) {
+                    self.feed(Index(index), meal);
+                }
+            }
+            return;
+        }
+
+        let farthest = self.reach.iter().copied().fold(0., f32::max);
+        let eaters = self
+            .food
+            .iter()
+            .map(|food| {
+                self.boops_near(*food, farthest)
+                    .map(|i| (i, self.boop(i).distance(*food)))
+                    .filter(|(i, d)| *d < self.reach[i.0])
+                    .min_by(|(a, da), (b, db)| da.total_cmp(db).then(a.cmp(b)))
+                    .map(|(i, _)| i)
+            })
+            .collect::<Vec<_>>();
+        if eaters.iter().all(Option::is_none) {
+            return;
+        }
+
+        for index in eaters.iter().flatten() {
+            self.feed(*index, meal);
+        }
+        let mut eaters = eaters.into_iter();
+        self.food.retain(|_| eaters.next().flatten().is_none());
+        self.food_grid.rebuild(&self.food);
+    }
+
+    /// Grows food back towards `count` and spreads patches, ending the day
+    pub fn replenish(&mut self, count: usize, forage: &Forage, rng: &mut impl rand::Rng) {
+        let before = self.food.len();
+        self.day += 1;
+
+        for _ in before..count {
+            if rng.gen::<f32>() < forage.respawn {
+                let food = forage.place(&self.terrain, &self.centres, self.day, rng);
+                self.food.push(food);
+            }
+        }
+
+        if let Some(growth) = &forage.growth {
+            for i in 0..self.food.len() {
+                if self.food.len() >= growth.limit {
+                    break;
+                }
+                if rng.gen::<f32>() < growth.rate {
+                    let sprout = food::around(&self.terrain, self.food[i], growth.spread, rng);
+                    self.food.push(sprout);
+                }
+            }
+        }
+
+        if self.food.len() != before {
+            self.food_grid.rebuild(&self.food);
+        }
     }
+
+    pub fn save(&self, writer: &mut impl Writer) -> std::io::Result<()> {
+        writer.write_u8(self.size())?;
+
+        writer.write_usize(self.boops.len())?;
+        for boop in &self.boops {
+            boop.save(writer)?;
+        }
+
+        writer.write_usize(self.food.len())?;
+        for food in &self.food {
+            food.save(writer)?;
+        }
+
+        self.terrain.save(writer)?;
+
+        writer.write_usize(self.centres.len())?;
+        for centre in &self.centres {
+            centre.save(writer)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn load(reader: &mut impl Reader) -> anyhow::Result<Self> {
+        let size = reader.read_u8()?;
+        if size == 0 {
+            anyhow::bail!("World size must not be zero");
+        }
+        let sizef = f32::from(size - 1);
+
+        let count = reader.read_usize()?;
+        let boops = (0..count)
+            .map(|_| Coordinate::load(reader, sizef))
+            .collect::<anyhow::Result<Vec<_>>>()?;
+
+        let count = reader.read_usize()?;
+        let food = (0..count)
+            .map(|_| Coordinate::load(reader, sizef))
+            .collect::<anyhow::Result<_>>()?;
+
+        let terrain = Terrain::load(reader, size)?;
+
+        let count = reader.read_usize()?;
+        let centres = (0..count)
+            .map(|_| Coordinate::load(reader, sizef))
+            .collect::<anyhow::Result<_>>()?;
+
+        let mut world = Self::with(terrain, boops, food);
+        world.centres = centres;
+        Ok(world)
+    }
+}
+
+/// Other boops around one, see [`World::neighbours`]
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Neighbours {
+    /// The closest one and its distance
+    pub nearest: Option<(Index, f32)>,
+    pub count: usize,
+}
+
+/// What a boop did during the current generation
+#[derive(Debug, Copy, Clone, Default)]
+pub struct Record {
+    /// Distance actually moved, after clamping to the borders
+    pub travelled: f32,
+    /// Days spent within reach of food
+    pub eaten: usize,
 }
 
 #[derive(Debug, Copy, Clone, PartialEq, PartialOrd)]
 pub struct Direction(f32);
 
 impl Direction {
-    pub fn random() -> Self {
-        Self(rand::random::<f32>() * std::f32::consts::TAU)
+    pub fn random(rng: &mut impl rand::Rng) -> Self {
+        Self(rng.gen::<f32>() * std::f32::consts::TAU)
+    }
+
+    #[cfg(test)]
+    pub fn from_rad(rad: f32) -> Self {
+        let mut direction = Self(rad);
+        direction.desaturate();
+        direction
     }
 
     fn desaturate(&mut self) {
@@ -90,6 +460,18 @@ impl Direction {
     pub fn as_rad(self) -> f32 {
         self.0
     }
+
+    pub fn save(self, writer: &mut impl Writer) -> std::io::Result<()> {
+        writer.write_f32(self.0)
+    }
+
+    pub fn load(reader: &mut impl Reader) -> anyhow::Result<Self> {
+        let rad = reader.read_f32()?;
+        if !(0.0..=std::f32::consts::TAU).contains(&rad) {
+            anyhow::bail!("Direction out of range: {rad}");
+        }
+        Ok(Self(rad))
+    }
 }
 
 impl std::ops::AddAssign<f32> for Direction {
@@ -161,6 +543,20 @@ impl Coordinate {
         let manhattan = self - rhs;
         (manhattan.0 * manhattan.0 + manhattan.1 * manhattan.1).sqrt()
     }
+
+    fn save(self, writer: &mut impl Writer) -> std::io::Result<()> {
+        writer.write_f32(self.0)?;
+        writer.write_f32(self.1)
+    }
+
+    fn load(reader: &mut impl Reader, max: f32) -> anyhow::Result<Self> {
+        let x = reader.read_f32()?;
+        let y = reader.read_f32()?;
+        if !(0.0..=max).contains(&x) || !(0.0..=max).contains(&y) {
+            anyhow::bail!("Coordinate out of bounds: ({x}, {y})");
+        }
+        Ok(Self(x, y))
+    }
 }
 
 impl std::ops::Sub for Coordinate {
@@ -188,6 +584,30 @@ mod test {
         }
     }
 
+    #[test]
+    fn walls_block() {
+        use super::{Index, World};
+        use crate::sim::Terrain;
+
+        let terrain = Terrain::parse("..#.\n..#.\n....\n....\n").unwrap();
+        let world = World::with(terrain, vec![Coordinate(1.5, 0.5)], vec![]);
+
+        // Straight into the wall
+        let east = Direction(0.);
+        assert_eq!(world.destination(Index(0), 1., east), Coordinate(1.5, 0.5));
+
+        // Sliding along it
+        let south_east = Direction(std::f32::consts::FRAC_PI_4);
+        let slid = world.destination(Index(0), 1., south_east);
+        assert!((slid.x() - 1.5).abs() < f32::EPSILON);
+        assert!(slid.y() > 0.5);
+
+        // Too fast to land inside the wall, not fast enough to jump over it
+        assert_eq!(world.destination(Index(0), 1.9, east), Coordinate(1.5, 0.5));
+        let fast = world.destination(Index(0), 1.9, Direction(std::f32::consts::FRAC_PI_2));
+        assert!((fast.y() - 2.4).abs() < 1e-5);
+    }
+
     #[test]
     fn dir_from() {
         let c1 = Coordinate(0., 0.);